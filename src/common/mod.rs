@@ -1,2 +1,4 @@
+#[cfg(unix)]
+pub mod unix;
 #[cfg(windows)]
 pub mod windows;
@@ -0,0 +1,53 @@
+#[cfg(feature = "fstab")]
+use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+#[cfg(feature = "mount")]
+use std::{ffi::OsString, os::unix::ffi::OsStringExt};
+
+/// Unescapes the octal sequences (e.g. `\040` for a space) used for special
+/// characters in files such as `/proc/self/mountinfo` and `/etc/fstab`.
+#[cfg(feature = "mount")]
+pub fn unescape(bytes: &[u8]) -> OsString {
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let octal = bytes
+            .get(i + 1..i + 4)
+            .filter(|_| bytes[i] == b'\\')
+            .filter(|digits| digits.iter().all(|d| (b'0'..=b'7').contains(d)))
+            .map(|digits| digits.iter().fold(0u16, |n, d| n * 8 + u16::from(d - b'0')));
+
+        match octal.and_then(|n| u8::try_from(n).ok()) {
+            Some(byte) => {
+                unescaped.push(byte);
+                i += 4;
+            }
+            None => {
+                unescaped.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    OsString::from_vec(unescaped)
+}
+
+/// Escapes whitespaces and backslashes as octal sequences. This is the inverse of
+/// [`unescape`].
+///
+/// Strings that are not valid UTF-8 have every non-ASCII byte escaped as well.
+#[cfg(feature = "fstab")]
+pub fn escape(s: &OsStr) -> String {
+    let utf8 = s.to_str().is_some();
+    let mut escaped = Vec::with_capacity(s.len());
+
+    for &byte in s.as_bytes() {
+        match byte {
+            b' ' | b'\t' | b'\n' | b'\\' => escaped.extend(format!("\\{byte:03o}").bytes()),
+            0x80..=0xff if !utf8 => escaped.extend(format!("\\{byte:03o}").bytes()),
+            byte => escaped.push(byte),
+        }
+    }
+
+    String::from_utf8(escaped).expect("escaped string is valid UTF-8")
+}
@@ -0,0 +1,442 @@
+use super::{Device, DeviceKind, Transport};
+use crate::mount::{mounts, MountEntry};
+use crate::{probe, Error, Result};
+use nix::libc;
+use nix::sys::statvfs::{self, Statvfs};
+use std::ffi::OsString;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::os::fd::AsRawFd;
+use std::os::unix::ffi::OsStringExt;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+const SYS_BLOCK: &str = "/sys/class/block";
+const UDEV_DATA: &str = "/run/udev/data";
+
+/// Size of the sectors `size` is counted in, regardless of the device.
+const SECTOR_SIZE: u64 = 512;
+
+mod ioctl {
+    use nix::libc::{c_int, c_uint};
+
+    nix::ioctl_read_bad!(blksszget, 0x1268, c_int);
+    nix::ioctl_read_bad!(blkpbszget, 0x127b, c_uint);
+}
+
+pub fn devices() -> Result<Vec<Device>> {
+    let mounts = mounts()?;
+    let mut devices = Vec::new();
+
+    for entry in fs::read_dir(SYS_BLOCK)? {
+        // Entries are links into the device tree, where the parent of a partition is its disk
+        let sys = match fs::canonicalize(entry?.path()) {
+            Ok(sys) => sys,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        match device(&sys, &mounts) {
+            Ok(device) => devices.push(device),
+            // The device was removed while being read
+            Err(Error::NotFound { .. }) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    devices.sort_by_key(|device| (device.major, device.minor));
+
+    Ok(devices)
+}
+
+pub fn parent(device: &Device) -> Result<Option<Device>> {
+    if device.kind != DeviceKind::Partition {
+        return Ok(None);
+    }
+
+    let Some(disk) = device.sys.parent() else {
+        return Ok(None);
+    };
+
+    Ok(Some(self::device(disk, &mounts()?)?))
+}
+
+pub fn partitions(device: &Device) -> Result<Vec<Device>> {
+    partitions_of(device, &mounts()?)
+}
+
+pub fn holders(device: &Device) -> Result<Vec<Device>> {
+    linked(&device.sys.join("holders"), &mounts()?)
+}
+
+pub fn slaves(device: &Device) -> Result<Vec<Device>> {
+    linked(&device.sys.join("slaves"), &mounts()?)
+}
+
+pub fn is_in_use(device: &Device) -> Result<bool> {
+    in_use(device, &mounts()?)
+}
+
+/// Checks partitions and holders recursively, with the same snapshot of the mount table.
+fn in_use(device: &Device, mounts: &[MountEntry]) -> Result<bool> {
+    if device.mount_point.is_some() {
+        return Ok(true);
+    }
+
+    let holders = linked(&device.sys.join("holders"), mounts)?;
+
+    for device in partitions_of(device, mounts)?.into_iter().chain(holders) {
+        if in_use(&device, mounts)? {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+fn partitions_of(device: &Device, mounts: &[MountEntry]) -> Result<Vec<Device>> {
+    if device.kind != DeviceKind::Disk {
+        return Ok(Vec::new());
+    }
+
+    let paths = fs::read_dir(&device.sys)?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.join("partition").exists());
+
+    related(paths, mounts)
+}
+
+/// Reads every device linked from a `holders` or `slaves` directory.
+fn linked(dir: &Path, mounts: &[MountEntry]) -> Result<Vec<Device>> {
+    let mut paths = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        match fs::canonicalize(entry?.path()) {
+            Ok(path) => paths.push(path),
+            // The device is being removed
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    related(paths.into_iter(), mounts)
+}
+
+fn related(paths: impl Iterator<Item = PathBuf>, mounts: &[MountEntry]) -> Result<Vec<Device>> {
+    let mut devices = Vec::new();
+
+    for path in paths {
+        match device(&path, mounts) {
+            Ok(device) => devices.push(device),
+            // The device was removed while being read
+            Err(Error::NotFound { .. }) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    devices.sort_by_key(|device| (device.major, device.minor));
+
+    Ok(devices)
+}
+
+fn device(sys: &Path, mounts: &[MountEntry]) -> Result<Device> {
+    let uevent = fs::read_to_string(sys.join("uevent"))?;
+    let name = uevent
+        .lines()
+        .find_map(|line| line.strip_prefix("DEVNAME="))
+        .map_or_else(
+            || sys.file_name().unwrap_or_default().into(),
+            OsString::from,
+        );
+
+    let (major, minor) = number(&fs::read_to_string(sys.join("dev"))?, sys)?;
+    let size = fs::read_to_string(sys.join("size"))?
+        .trim()
+        .parse::<u64>()
+        .map_err(|_| invalid(sys, "size"))?
+        * SECTOR_SIZE;
+
+    let kind = if sys.join("partition").exists() {
+        DeviceKind::Partition
+    } else {
+        DeviceKind::Disk
+    };
+
+    // Hardware properties of a partition are those of its disk
+    let disk = match kind {
+        DeviceKind::Partition => sys.parent().unwrap_or(sys),
+        DeviceKind::Disk => sys,
+    };
+
+    let udev = fs::read_to_string(Path::new(UDEV_DATA).join(format!("b{major}:{minor}"))).ok();
+    let property = |key: &str| udev.as_deref().and_then(|data| property(data, key));
+    let transport = transport(disk);
+
+    let path = Path::new("/dev").join(&name);
+
+    let mut logical_sector_size = number_attribute(&disk.join("queue/logical_block_size"));
+    let mut physical_sector_size = number_attribute(&disk.join("queue/physical_block_size"));
+
+    // Some devices have no request queue in sysfs, but still answer the ioctls
+    if logical_sector_size == 0 || physical_sector_size == 0 {
+        if let Some((logical, physical)) = sector_sizes(&path) {
+            (logical_sector_size, physical_sector_size) = (logical, physical);
+        }
+    }
+    let mount = mounts
+        .iter()
+        .filter(|entry| (entry.major(), entry.minor()) == (major, minor))
+        .min_by_key(|entry| entry.root() != Path::new("/"));
+
+    let (mut file_system, label) = match property("ID_FS_TYPE").filter(|t| !t.is_empty()) {
+        Some(fs_type) => {
+            let label = property("ID_FS_LABEL_ENC")
+                .map(decode)
+                .or_else(|| property("ID_FS_LABEL").map(OsString::from));
+
+            (Some(fs_type.into()), label)
+        }
+        None => superblock(&path),
+    };
+
+    if file_system.is_none() {
+        file_system = mount.map(|entry| entry.fs_type().to_os_string());
+    }
+
+    let space = mount
+        .and_then(|entry| statvfs::statvfs(entry.mount_point()).ok())
+        .map(|stat| space(&stat));
+
+    Ok(Device {
+        name,
+        path,
+        kind,
+        major,
+        minor,
+        size,
+        logical_sector_size,
+        physical_sector_size,
+        removable: number_attribute(&disk.join("removable")) == 1,
+        read_only: number_attribute(&sys.join("ro")) == 1,
+        rotational: number_attribute(&disk.join("queue/rotational")) == 1,
+        model: attribute(&disk.join("device/model"))
+            .or_else(|| attribute(&disk.join("device/name")))
+            .or_else(|| property("ID_MODEL").map(Into::into)),
+        // virtio reports the PCI vendor id instead
+        vendor: attribute(&disk.join("device/vendor"))
+            .filter(|_| transport != Some(Transport::Virtio))
+            .or_else(|| property("ID_VENDOR").map(Into::into)),
+        serial: attribute(&disk.join("serial"))
+            .or_else(|| attribute(&disk.join("device/serial")))
+            .or_else(|| property("ID_SERIAL_SHORT").map(Into::into)),
+        wwn: attribute(&disk.join("wwid"))
+            .or_else(|| attribute(&disk.join("device/wwid")))
+            .or_else(|| property("ID_WWN").map(Into::into)),
+        transport,
+        discard_granularity: number_attribute(&disk.join("queue/discard_granularity")),
+        mount_point: mount.map(|entry| entry.mount_point().to_path_buf()),
+        file_system,
+        label,
+        total: space.map(|(total, _)| total),
+        available: space.map(|(_, available)| available),
+        sys: sys.to_path_buf(),
+    })
+}
+
+/// Returns the total and available space of a file system, in bytes.
+#[allow(clippy::unnecessary_cast)] // The field types differ between targets
+fn space(stat: &Statvfs) -> (u64, u64) {
+    let fragment = stat.fragment_size() as u64;
+    (
+        stat.blocks() as u64 * fragment,
+        stat.blocks_available() as u64 * fragment,
+    )
+}
+
+/// Parses a device number in the `major:minor` form, read from the `dev` attribute of
+/// the device at `sys`.
+fn number(dev: &str, sys: &Path) -> Result<(u32, u32)> {
+    dev.trim()
+        .split_once(':')
+        .and_then(|(major, minor)| Some((major.parse().ok()?, minor.parse().ok()?)))
+        .ok_or_else(|| invalid(sys, "dev"))
+}
+
+fn invalid(sys: &Path, attribute: &str) -> Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("malformed {}", sys.join(attribute).display()),
+    )
+    .into()
+}
+
+/// Reads a sysfs attribute. Returns `None` if it does not exist or is empty.
+fn attribute(path: &Path) -> Option<String> {
+    let value = fs::read_to_string(path).ok()?;
+    let value = value.trim();
+
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Reads a numeric sysfs attribute. Returns 0 if it does not exist.
+fn number_attribute(path: &Path) -> u64 {
+    attribute(path)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_default()
+}
+
+/// Finds the value of `key` in a device entry of the udev database.
+fn property<'a>(data: &'a str, key: &str) -> Option<&'a str> {
+    data.lines()
+        .filter_map(|line| line.strip_prefix("E:"))
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+}
+
+/// Tells how a disk is connected from the subsystems of the devices it hangs from in the
+/// device tree, e.g. a SCSI disk on a USB interface is a USB disk.
+fn transport(disk: &Path) -> Option<Transport> {
+    let mut subsystems = Vec::new();
+    let mut sata = false;
+
+    for dir in disk
+        .ancestors()
+        .take_while(|dir| dir.starts_with("/sys/devices"))
+    {
+        if let Ok(subsystem) = fs::read_link(dir.join("subsystem")) {
+            subsystems.extend(subsystem.file_name().map(OsString::from));
+        }
+
+        // libata ports have no subsystem of their own, only an `ata_port` class device
+        sata |= dir.join("ata_port").is_dir();
+    }
+
+    let has = |name: &str| subsystems.iter().any(|subsystem| subsystem == name);
+
+    // USB and SATA disks go through the SCSI layer as well
+    if has("usb") {
+        Some(Transport::Usb)
+    } else if has("nvme") {
+        Some(Transport::Nvme)
+    } else if has("virtio") {
+        Some(Transport::Virtio)
+    } else if has("mmc") {
+        Some(Transport::Mmc)
+    } else if sata {
+        Some(Transport::Sata)
+    } else if has("scsi") {
+        Some(Transport::Scsi)
+    } else {
+        None
+    }
+}
+
+/// Reads the logical and physical sector sizes of a device with the `BLKSSZGET` and
+/// `BLKPBSZGET` ioctls. Returns `None` if the device can't be opened.
+fn sector_sizes(path: &Path) -> Option<(u64, u64)> {
+    let file = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)
+        .ok()?;
+
+    let (mut logical, mut physical) = (0, 0);
+    unsafe { ioctl::blksszget(file.as_raw_fd(), &mut logical).ok()? };
+    unsafe { ioctl::blkpbszget(file.as_raw_fd(), &mut physical).ok()? };
+
+    Some((logical as u64, physical as u64))
+}
+
+/// Decodes the `\xNN` sequences udev uses to escape special characters.
+fn decode(encoded: &str) -> OsString {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i..i + 4)
+            .filter(|seq| seq.starts_with(b"\\x"))
+            .and_then(|seq| std::str::from_utf8(&seq[2..]).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 4;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    OsString::from_vec(decoded)
+}
+
+/// Reads the file system and label of a device from its superblock. Devices that can't
+/// be opened, usually for lack of permissions, are reported as having neither.
+fn superblock(path: &Path) -> (Option<OsString>, Option<OsString>) {
+    let probe = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)
+        .ok()
+        .and_then(|mut file| probe::probe(&mut file).ok().flatten());
+
+    match probe {
+        Some(probe) => (Some(probe.fs_type().into()), probe.label().map(Into::into)),
+        None => (None, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::ffi::OsStrExt;
+
+    #[test]
+    fn numbers() {
+        let sys = Path::new("/sys/class/block/sda1");
+        assert_eq!(number("8:1\n", sys).unwrap(), (8, 1));
+        assert_eq!(number("259:12", sys).unwrap(), (259, 12));
+
+        for dev in ["", "8", "8:", ":1", "8:x", "-8:1"] {
+            let error = number(dev, sys).unwrap_err();
+            assert_eq!(error.to_string(), "malformed /sys/class/block/sda1/dev");
+        }
+    }
+
+    #[test]
+    fn properties() {
+        let data = "\
+S:disk/by-uuid/0a1b2c3d
+I:1234567
+E:ID_FS_UUID=0a1b2c3d
+E:ID_FS_LABEL_ENC=My\\x20Data
+E:ID_FS_LABEL=My_Data
+E:ID_PART_ENTRY_NAME=
+G:systemd
+";
+        assert_eq!(property(data, "ID_FS_UUID"), Some("0a1b2c3d"));
+        assert_eq!(property(data, "ID_FS_LABEL"), Some("My_Data"));
+        assert_eq!(property(data, "ID_FS_LABEL_ENC"), Some("My\\x20Data"));
+        assert_eq!(property(data, "ID_PART_ENTRY_NAME"), Some(""));
+        assert_eq!(property(data, "ID_FS"), None);
+        assert_eq!(property(data, "disk/by-uuid/0a1b2c3d"), None);
+        assert_eq!(property("", "ID_FS_UUID"), None);
+    }
+
+    #[test]
+    fn decoding() {
+        assert_eq!(decode("My\\x20Data"), "My Data");
+        assert_eq!(decode("a\\x2fb\\x5cc"), "a/b\\c");
+        assert_eq!(decode("caf\\xc3\\xa9"), "caf\u{e9}");
+        assert_eq!(decode("\\xff").as_bytes(), b"\xff");
+
+        // Incomplete or invalid sequences are kept as they are
+        assert_eq!(decode("end\\x2"), "end\\x2");
+        assert_eq!(decode("\\xzz"), "\\xzz");
+        assert_eq!(decode("\\"), "\\");
+    }
+}
@@ -1,32 +1,322 @@
-#![allow(dead_code)]
-#![allow(unused)]
+//! Block devices and the file systems on them.
+//!
+//! Cross-platform abstraction for listing disks and partitions along with where, and as
+//! what, they are mounted. Partition tables and superblocks can also be read directly from
+//! a disk or an image file with [`partition_table`] and [`probe`].
 
-use std::ffi::OsStr;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod linux;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use linux as sys;
 
-pub struct Device {}
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+mod unsupported;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+use unsupported as sys;
+
+pub mod partition_table;
+pub mod probe;
+
+use crate::Result;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+/// What a [`Device`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DeviceKind {
+    /// A whole disk, including virtual ones such as loop devices.
+    Disk,
+    /// A partition of a disk.
+    Partition,
+}
+
+/// How a disk is connected to the machine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Transport {
+    /// USB mass storage.
+    Usb,
+    /// SATA or PATA, through libata.
+    Sata,
+    /// SCSI and SAS, along with anything else exposed through the SCSI layer.
+    Scsi,
+    /// NVM Express.
+    Nvme,
+    /// A virtio block device of a virtual machine.
+    Virtio,
+    /// SD and eMMC cards.
+    Mmc,
+}
+
+/// A block device.
+///
+/// Every field is read when the device is listed by [`devices`], so it is a snapshot that
+/// is not updated afterwards.
+///
+/// Devices form a tree: a disk is the parent of its partitions, and devices stacked on top
+/// of others, such as device mapper (LVM, LUKS) or software RAID devices, are holders of
+/// the devices they are built from, which are their slaves.
+///
+/// # Examples
+///
+/// Find the disks backing the file system mounted at `/home`, even through LVM:
+///
+/// ```no_run
+/// use disket::device::{self, Device, DeviceKind};
+/// use std::error::Error;
+///
+/// fn disks(device: Device) -> Result<Vec<Device>, Box<dyn Error>> {
+///     let slaves = device.slaves()?;
+///
+///     if !slaves.is_empty() {
+///         let mut found = Vec::new();
+///         for slave in slaves {
+///             found.extend(disks(slave)?);
+///         }
+///
+///         return Ok(found);
+///     }
+///
+///     match device.kind() {
+///         DeviceKind::Partition => Ok(device.parent()?.into_iter().collect()),
+///         DeviceKind::Disk => Ok(vec![device]),
+///     }
+/// }
+///
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     let home = device::devices()?
+///         .into_iter()
+///         .find(|device| device.mount_point() == Some("/home".as_ref()))
+///         .ok_or("/home is not mounted")?;
+///
+///     for disk in disks(home)? {
+///         println!("{}", disk.path().display());
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Device {
+    name: OsString,
+    path: PathBuf,
+    kind: DeviceKind,
+    major: u32,
+    minor: u32,
+    size: u64,
+    logical_sector_size: u64,
+    physical_sector_size: u64,
+    removable: bool,
+    read_only: bool,
+    rotational: bool,
+    model: Option<String>,
+    vendor: Option<String>,
+    serial: Option<String>,
+    wwn: Option<String>,
+    transport: Option<Transport>,
+    discard_granularity: u64,
+    mount_point: Option<PathBuf>,
+    file_system: Option<OsString>,
+    label: Option<OsString>,
+    total: Option<u64>,
+    available: Option<u64>,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    sys: PathBuf,
+}
 
 impl Device {
+    /// Name of the device, such as `sda1`.
     pub fn name(&self) -> &OsStr {
-        todo!();
+        &self.name
+    }
+
+    /// Path of the device node, such as `/dev/sda1`.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the device is a disk or a partition.
+    pub fn kind(&self) -> DeviceKind {
+        self.kind
+    }
+
+    /// Major number of the device.
+    pub fn major(&self) -> u32 {
+        self.major
+    }
+
+    /// Minor number of the device.
+    pub fn minor(&self) -> u32 {
+        self.minor
+    }
+
+    /// Size of the device in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Smallest unit the device can address, in bytes. Usually 512.
+    pub fn logical_sector_size(&self) -> u64 {
+        self.logical_sector_size
+    }
+
+    /// Smallest unit the device can write without a read-modify-write cycle, in bytes.
+    pub fn physical_sector_size(&self) -> u64 {
+        self.physical_sector_size
+    }
+
+    /// Whether the media can be removed from the device, as with card readers and optical
+    /// drives. USB disks usually report `false`; check [`Device::transport`] for them.
+    pub fn is_removable(&self) -> bool {
+        self.removable
+    }
+
+    /// Whether the device is read-only.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
     }
 
-    pub fn label(&self) -> &OsStr {
-        todo!();
+    /// Whether the device is a spinning disk, as reported by its driver.
+    pub fn is_rotational(&self) -> bool {
+        self.rotational
     }
 
-    pub fn mount_point(&self) -> &OsStr {
-        todo!();
+    /// Model of the disk, if known.
+    pub fn model(&self) -> Option<&str> {
+        self.model.as_deref()
     }
 
-    pub fn file_system(&self) -> &OsStr {
-        todo!();
+    /// Vendor of the disk, if known.
+    pub fn vendor(&self) -> Option<&str> {
+        self.vendor.as_deref()
     }
 
-    pub fn total(&self) -> usize {
-        todo!();
+    /// Serial number of the disk, if known.
+    pub fn serial(&self) -> Option<&str> {
+        self.serial.as_deref()
     }
 
-    pub fn available(&self) -> usize {
-        todo!();
+    /// World Wide Name of the disk, if it has one.
+    pub fn wwn(&self) -> Option<&str> {
+        self.wwn.as_deref()
     }
+
+    /// How the disk is connected, or `None` for virtual devices such as loop devices.
+    pub fn transport(&self) -> Option<Transport> {
+        self.transport
+    }
+
+    /// Granularity of discard (TRIM) operations in bytes, or 0 if the device does not
+    /// support discarding.
+    pub fn discard_granularity(&self) -> u64 {
+        self.discard_granularity
+    }
+
+    /// Label of the file system on the device, if it has one.
+    pub fn label(&self) -> Option<&OsStr> {
+        self.label.as_deref()
+    }
+
+    /// Path where the device is mounted, if it is mounted.
+    ///
+    /// If the device is mounted more than once, the mount of the root of its file system
+    /// is preferred.
+    pub fn mount_point(&self) -> Option<&Path> {
+        self.mount_point.as_deref()
+    }
+
+    /// Type of the file system on the device, such as `ext4`, if it is known.
+    pub fn file_system(&self) -> Option<&OsStr> {
+        self.file_system.as_deref()
+    }
+
+    /// Size of the mounted file system in bytes.
+    ///
+    /// Returns `None` if the device is not mounted.
+    pub fn total(&self) -> Option<u64> {
+        self.total
+    }
+
+    /// Space available to unprivileged users in the mounted file system, in bytes.
+    ///
+    /// Returns `None` if the device is not mounted.
+    pub fn available(&self) -> Option<u64> {
+        self.available
+    }
+
+    /// Returns the disk this partition belongs to, or `None` if the device is a disk.
+    pub fn parent(&self) -> Result<Option<Device>> {
+        sys::parent(self)
+    }
+
+    /// Returns the partitions of this disk, sorted by number. Partitions have none.
+    pub fn partitions(&self) -> Result<Vec<Device>> {
+        sys::partitions(self)
+    }
+
+    /// Returns the devices stacked on top of this one, such as the device mapper devices
+    /// of LVM logical volumes or the RAID array the device is a member of.
+    pub fn holders(&self) -> Result<Vec<Device>> {
+        sys::holders(self)
+    }
+
+    /// Returns the devices this one is stacked on top of. This is the opposite of
+    /// [`Device::holders`].
+    pub fn slaves(&self) -> Result<Vec<Device>> {
+        sys::slaves(self)
+    }
+
+    /// Returns `true` if this device, or any device on it, is mounted.
+    ///
+    /// Partitions and holders are checked recursively, so a disk is in use if one of its
+    /// partitions is an LVM physical volume with a mounted logical volume. Swap and other
+    /// uses that don't involve mounting are not detected.
+    pub fn is_in_use(&self) -> Result<bool> {
+        sys::is_in_use(self)
+    }
+}
+
+/// Returns every block device, disks and partitions alike.
+///
+/// Devices are sorted by their device number, so partitions come right after their disk.
+///
+/// # Platform-specific behaviour
+///
+/// On Linux and Android, devices are found in `/sys/class/block`. The file system and label
+/// are read from the udev database, or from the superblock when udev does not know the
+/// device, with [`probe`]. Reading superblocks usually requires root.
+///
+/// The hierarchy is read from the `holders` and `slaves` directories of each device. Partitions
+/// report the hardware properties, such as the model or sector sizes, of their disk. Model,
+/// vendor, serial and WWN fall back to the udev database when sysfs does not have them, and
+/// sector sizes to the `BLKSSZGET` and `BLKPBSZGET` ioctls. The transport is found from the
+/// subsystems of the devices the disk hangs from.
+///
+/// Not supported on FreeBSD, macOS, IOS and Windows yet.
+///
+/// # Errors
+///
+/// Returns an [`crate::Error::Unsupported`] error where listing devices is not supported.
+///
+/// # Examples
+///
+/// ```no_run
+/// use disket::device;
+/// use std::error::Error;
+///
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     for device in device::devices()? {
+///         println!(
+///             "{:<10} {:>14} {:<6} {}",
+///             device.name().to_string_lossy(),
+///             device.size(),
+///             device.file_system().unwrap_or_default().to_string_lossy(),
+///             device.mount_point().unwrap_or("".as_ref()).display(),
+///         );
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub fn devices() -> Result<Vec<Device>> {
+    sys::devices()
 }
@@ -0,0 +1,39 @@
+//! Partition tables of disks and disk images.
+//!
+//! GPT and MBR partition tables are parsed in pure Rust from any
+//! [`Read`](std::io::Read) + [`Seek`](std::io::Seek) source, be it a block device or an
+//! image file. Nothing goes through the kernel, so this works the same on every platform
+//! and images can be inspected without a loop device.
+//!
+//! Tables can also be created from scratch or edited, with partitions aligned to 1 MiB,
+//! and written back with [`PartitionTable::write`], which keeps the backup GPT in sync and
+//! tells the kernel about the new partitions.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use disket::device::partition_table::PartitionTable;
+//! use std::error::Error;
+//! use std::fs::File;
+//!
+//! fn main() -> Result<(), Box<dyn Error>> {
+//!     let mut image = File::open("disk.img")?;
+//!     let table = PartitionTable::read(&mut image)?.ok_or("no partition table")?;
+//!
+//!     for partition in table.partitions() {
+//!         println!(
+//!             "{:>3} {:>12} {:>12} {}",
+//!             partition.number(),
+//!             partition.start_lba(),
+//!             partition.end_lba(),
+//!             partition.name().unwrap_or_default(),
+//!         );
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+pub use crate::partition_table::{
+    Guid, Partition, PartitionOptions, PartitionTable, PartitionType, TableKind,
+};
@@ -0,0 +1,34 @@
+//! Detection of file systems and other content from their superblock.
+//!
+//! Like libblkid, superblocks are parsed in pure Rust from any
+//! [`Read`](std::io::Read) + [`Seek`](std::io::Seek) source, be it a block device or an
+//! image file. Neither udev nor the kernel is involved, so this works in containers and
+//! initramfs, and on images that are not attached to anything.
+//!
+//! The type, UUID, label, version and block size are found for ext2/3/4, xfs, btrfs,
+//! FAT12/16/32, exFAT, NTFS, iso9660, UDF, squashfs, swap, LUKS and LVM2 physical volumes,
+//! as far as each format records them.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use disket::device::probe::{self, Usage};
+//! use std::error::Error;
+//! use std::fs::File;
+//!
+//! fn main() -> Result<(), Box<dyn Error>> {
+//!     let mut device = File::open("/dev/sda1")?;
+//!
+//!     match probe::probe(&mut device)? {
+//!         Some(probe) if probe.usage() == Usage::FileSystem => {
+//!             println!("{} file system, version {:?}", probe.fs_type(), probe.version())
+//!         }
+//!         Some(probe) => println!("{} volume", probe.fs_type()),
+//!         None => println!("nothing found"),
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+pub use crate::probe::{probe, Probe, Usage};
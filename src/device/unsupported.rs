@@ -0,0 +1,27 @@
+use super::Device;
+use crate::Result;
+use std::io;
+
+pub fn devices() -> Result<Vec<Device>> {
+    Err(io::Error::from(io::ErrorKind::Unsupported).into())
+}
+
+pub fn parent(_: &Device) -> Result<Option<Device>> {
+    Err(io::Error::from(io::ErrorKind::Unsupported).into())
+}
+
+pub fn partitions(_: &Device) -> Result<Vec<Device>> {
+    Err(io::Error::from(io::ErrorKind::Unsupported).into())
+}
+
+pub fn holders(_: &Device) -> Result<Vec<Device>> {
+    Err(io::Error::from(io::ErrorKind::Unsupported).into())
+}
+
+pub fn slaves(_: &Device) -> Result<Vec<Device>> {
+    Err(io::Error::from(io::ErrorKind::Unsupported).into())
+}
+
+pub fn is_in_use(_: &Device) -> Result<bool> {
+    Err(io::Error::from(io::ErrorKind::Unsupported).into())
+}
@@ -1,14 +1,488 @@
-use std::result;
+use std::fmt;
+use std::path::PathBuf;
+use std::{io, result};
 
 pub type Result<T> = result::Result<T, Error>;
 
-// TODO: write actual messages
+/// Errors returned by every operation of this crate.
+///
+/// Most variants describe what went wrong along with the operation that failed, such as
+/// `mount` or `unmount`, and the paths it was given. The operation is empty, and there are
+/// no paths, when the error does not come from one of them. The error reported by the
+/// operating system, if any, is kept as the source.
+///
+/// Errors can be converted to [`io::Error`], with the matching [`io::ErrorKind`].
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
+    /// A file, device or mount point does not exist.
+    #[error("{}not found", prefix(operation, paths))]
+    NotFound {
+        operation: &'static str,
+        paths: Vec<PathBuf>,
+        source: Option<OsError>,
+    },
+    /// The caller is not allowed to perform the operation.
+    #[error("{}permission denied", prefix(operation, paths))]
+    PermissionDenied {
+        operation: &'static str,
+        paths: Vec<PathBuf>,
+        source: Option<OsError>,
+    },
+    /// The file system or device is in use.
+    #[error("{}resource busy", prefix(operation, paths))]
+    Busy {
+        operation: &'static str,
+        paths: Vec<PathBuf>,
+        source: Option<OsError>,
+    },
+    /// The path is not a mount point.
+    #[error("{}not mounted", prefix(operation, paths))]
+    NotMounted {
+        operation: &'static str,
+        paths: Vec<PathBuf>,
+        source: Option<OsError>,
+    },
+    /// The volume is already mounted.
+    #[error("{}already mounted", prefix(operation, paths))]
+    AlreadyMounted {
+        operation: &'static str,
+        paths: Vec<PathBuf>,
+        source: Option<OsError>,
+    },
+    /// The options, or one of the arguments, were rejected.
+    #[error("{}invalid options", prefix(operation, paths))]
+    InvalidOptions {
+        operation: &'static str,
+        paths: Vec<PathBuf>,
+        source: Option<OsError>,
+    },
+    /// The file system type is not known by the operating system.
+    #[error("{}unknown file system", prefix(operation, paths))]
+    UnknownFileSystem {
+        operation: &'static str,
+        paths: Vec<PathBuf>,
+        source: Option<OsError>,
+    },
+    /// The operation, or one of its options, is not supported on this platform.
+    #[error("{}not supported", prefix(operation, paths))]
+    Unsupported {
+        operation: &'static str,
+        paths: Vec<PathBuf>,
+        source: Option<OsError>,
+    },
+    /// Any other error reported by the operating system. Its message is only shown by the
+    /// source.
+    #[error("{}os error {}", prefix(operation, paths), source.code())]
+    Os {
+        operation: &'static str,
+        paths: Vec<PathBuf>,
+        source: OsError,
+    },
+    /// An I/O error that is not reported by the operating system, such as malformed data.
+    #[error(transparent)]
+    Io(io::Error),
+    /// Some of the mounts of a recursive unmount could not be unmounted. Holds the mount
+    /// point and error of each of them.
+    #[error("recursive unmount failed: {}", failures.iter().map(|(_, e)| e.to_string()).collect::<Vec<_>>().join(", "))]
+    Unmount { failures: Vec<(PathBuf, Error)> },
+}
+
+impl Error {
+    /// Returns the raw error reported by the operating system, if any.
+    ///
+    /// This is `errno` on *nix systems and a `HRESULT` on Windows.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        self.os_error().map(OsError::code)
+    }
+
+    fn os_error(&self) -> Option<&OsError> {
+        match self {
+            Error::NotFound { source, .. }
+            | Error::PermissionDenied { source, .. }
+            | Error::Busy { source, .. }
+            | Error::NotMounted { source, .. }
+            | Error::AlreadyMounted { source, .. }
+            | Error::InvalidOptions { source, .. }
+            | Error::UnknownFileSystem { source, .. }
+            | Error::Unsupported { source, .. } => source.as_ref(),
+            Error::Os { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+
+    /// Sets the operation that failed and the paths it was given, unless they are
+    /// already set.
+    ///
+    /// Some errors mean something more specific for some operations, as told by
+    /// [`Operation`], e.g. `EINVAL` from [`Operation::Unmount`] means the path is not a
+    /// mount point.
+    pub(crate) fn context<P: Into<PathBuf>>(
+        self,
+        operation: impl Into<Operation>,
+        paths: impl IntoIterator<Item = P>,
+    ) -> Self {
+        let paths = || {
+            paths
+                .into_iter()
+                .map(Into::into)
+                .filter(|path: &PathBuf| !path.as_os_str().is_empty())
+                .collect()
+        };
+
+        let kind = operation.into();
+        let operation = kind.name();
+
+        match (self, kind) {
+            (
+                Error::InvalidOptions {
+                    operation: "",
+                    source,
+                    ..
+                },
+                Operation::Unmount,
+            ) => Error::NotMounted {
+                operation,
+                paths: paths(),
+                source,
+            },
+            (
+                Error::Busy {
+                    operation: "",
+                    source,
+                    ..
+                },
+                Operation::Mount,
+            ) => Error::AlreadyMounted {
+                operation,
+                paths: paths(),
+                source,
+            },
+            (mut error, _) => {
+                match &mut error {
+                    Error::NotFound {
+                        operation: empty @ "",
+                        paths: found,
+                        ..
+                    }
+                    | Error::PermissionDenied {
+                        operation: empty @ "",
+                        paths: found,
+                        ..
+                    }
+                    | Error::Busy {
+                        operation: empty @ "",
+                        paths: found,
+                        ..
+                    }
+                    | Error::NotMounted {
+                        operation: empty @ "",
+                        paths: found,
+                        ..
+                    }
+                    | Error::AlreadyMounted {
+                        operation: empty @ "",
+                        paths: found,
+                        ..
+                    }
+                    | Error::InvalidOptions {
+                        operation: empty @ "",
+                        paths: found,
+                        ..
+                    }
+                    | Error::UnknownFileSystem {
+                        operation: empty @ "",
+                        paths: found,
+                        ..
+                    }
+                    | Error::Unsupported {
+                        operation: empty @ "",
+                        paths: found,
+                        ..
+                    }
+                    | Error::Os {
+                        operation: empty @ "",
+                        paths: found,
+                        ..
+                    } => {
+                        *empty = operation;
+                        *found = paths();
+                    }
+                    _ => {}
+                }
+
+                error
+            }
+        }
+    }
+}
+
+/// An operation given to [`Error::context`].
+#[cfg_attr(not(feature = "mount"), allow(dead_code))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Operation {
+    /// Mounting, where `EBUSY` means the volume is already mounted.
+    Mount,
+    /// Unmounting, where `EINVAL` means the path is not a mount point.
+    Unmount,
+    /// Any other operation, named by the string.
+    Other(&'static str),
+}
+
+impl Operation {
+    fn name(self) -> &'static str {
+        match self {
+            Operation::Mount => "mount",
+            Operation::Unmount => "unmount",
+            Operation::Other(name) => name,
+        }
+    }
+}
+
+impl From<&'static str> for Operation {
+    fn from(name: &'static str) -> Self {
+        Operation::Other(name)
+    }
+}
+
+/// Formats the operation and paths of an error, followed by a colon.
+fn prefix(operation: &str, paths: &[PathBuf]) -> String {
+    if operation.is_empty() {
+        return String::new();
+    }
+
+    let mut prefix = operation.to_string();
+
+    for path in paths {
+        prefix.push(' ');
+        prefix.push_str(&path.to_string_lossy());
+    }
+
+    prefix + ": "
+}
+
+impl From<OsError> for Error {
+    fn from(source: OsError) -> Self {
+        let (operation, paths) = ("", Vec::new());
+
+        match source.class() {
+            Class::NotFound => Error::NotFound {
+                operation,
+                paths,
+                source: Some(source),
+            },
+            Class::PermissionDenied => Error::PermissionDenied {
+                operation,
+                paths,
+                source: Some(source),
+            },
+            Class::Busy => Error::Busy {
+                operation,
+                paths,
+                source: Some(source),
+            },
+            Class::InvalidOptions => Error::InvalidOptions {
+                operation,
+                paths,
+                source: Some(source),
+            },
+            Class::UnknownFileSystem => Error::UnknownFileSystem {
+                operation,
+                paths,
+                source: Some(source),
+            },
+            Class::Unsupported => Error::Unsupported {
+                operation,
+                paths,
+                source: Some(source),
+            },
+            Class::Other => Error::Os {
+                operation,
+                paths,
+                source,
+            },
+        }
+    }
+}
+
+#[cfg(unix)]
+impl From<nix::Error> for Error {
+    fn from(errno: nix::Error) -> Self {
+        OsError::new(errno as i32).into()
+    }
+}
+
+#[cfg(windows)]
+impl From<windows::core::Error> for Error {
+    fn from(error: windows::core::Error) -> Self {
+        OsError::new(error.code().0).into()
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        match error.raw_os_error() {
+            #[cfg(unix)]
+            Some(code) => OsError::new(code).into(),
+            #[cfg(windows)]
+            Some(code) => OsError::new(windows::core::HRESULT::from_win32(code as u32).0).into(),
+            _ if error.kind() == io::ErrorKind::Unsupported => Error::Unsupported {
+                operation: "",
+                paths: Vec::new(),
+                source: None,
+            },
+            _ => Error::Io(error),
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(error: Error) -> Self {
+        if let Error::Io(error) = error {
+            return error;
+        }
+
+        let kind = match &error {
+            Error::NotFound { .. } => io::ErrorKind::NotFound,
+            Error::PermissionDenied { .. } => io::ErrorKind::PermissionDenied,
+            Error::AlreadyMounted { .. } => io::ErrorKind::AlreadyExists,
+            Error::InvalidOptions { .. }
+            | Error::NotMounted { .. }
+            | Error::UnknownFileSystem { .. } => io::ErrorKind::InvalidInput,
+            Error::Unsupported { .. } => io::ErrorKind::Unsupported,
+            error => error.os_error().map_or(io::ErrorKind::Other, OsError::kind),
+        };
+
+        io::Error::new(kind, error)
+    }
+}
+
+/// An error reported by the operating system.
+///
+/// On Linux, it may also hold the messages the kernel logged while mounting, which usually
+/// tell which option was rejected and why.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OsError {
+    code: i32,
+    messages: Vec<String>,
+}
+
+impl OsError {
+    pub(crate) fn new(code: i32) -> Self {
+        Self {
+            code,
+            messages: Vec::new(),
+        }
+    }
+
+    #[cfg(all(feature = "mount", any(target_os = "linux", target_os = "android")))]
+    pub(crate) fn with_messages(code: i32, messages: Vec<String>) -> Self {
+        Self { code, messages }
+    }
+
+    /// Returns the raw error code: `errno` on *nix systems and a `HRESULT` on Windows.
+    pub fn code(&self) -> i32 {
+        self.code
+    }
+
+    /// Returns the messages logged by the kernel along with the error.
+    ///
+    /// Only filled on Linux when mounting through a file system context.
+    pub fn messages(&self) -> &[String] {
+        &self.messages
+    }
+
+    /// Returns the matching Win32 error code if the `HRESULT` wraps one.
     #[cfg(windows)]
-    #[error("")]
-    Platform(#[from] windows::core::Error),
+    fn win32(&self) -> Option<i32> {
+        ((self.code as u32) >> 16 == 0x8007).then_some(self.code & 0xffff)
+    }
+
+    fn kind(&self) -> io::ErrorKind {
+        #[cfg(unix)]
+        let code = Some(self.code);
+        #[cfg(windows)]
+        let code = self.win32();
+
+        code.map_or(io::ErrorKind::Other, |code| {
+            io::Error::from_raw_os_error(code).kind()
+        })
+    }
+
     #[cfg(unix)]
-    #[error("")]
-    Platform(#[from] nix::Error),
+    fn class(&self) -> Class {
+        use nix::errno::Errno;
+
+        let errno = Errno::from_raw(self.code);
+        let classes: &[(&[Errno], Class)] = &[
+            (&[Errno::ENOENT, Errno::ENXIO], Class::NotFound),
+            (&[Errno::EACCES, Errno::EPERM], Class::PermissionDenied),
+            (&[Errno::EBUSY], Class::Busy),
+            (&[Errno::EINVAL], Class::InvalidOptions),
+            (&[Errno::ENODEV], Class::UnknownFileSystem),
+            (
+                &[Errno::ENOSYS, Errno::ENOTSUP, Errno::EOPNOTSUPP],
+                Class::Unsupported,
+            ),
+        ];
+
+        classes
+            .iter()
+            .find(|(errnos, _)| errnos.contains(&errno))
+            .map_or(Class::Other, |(_, class)| *class)
+    }
+
+    #[cfg(windows)]
+    fn class(&self) -> Class {
+        let Some(code) = self.win32() else {
+            return Class::Other;
+        };
+
+        // Win32 error codes, from `winerror.h`
+        let classes: &[(&[i32], Class)] = &[
+            (&[2, 3], Class::NotFound),
+            (&[5], Class::PermissionDenied),
+            (&[32, 170], Class::Busy),
+            (&[87], Class::InvalidOptions),
+            (&[1005], Class::UnknownFileSystem),
+            (&[50], Class::Unsupported),
+        ];
+
+        classes
+            .iter()
+            .find(|(codes, _)| codes.contains(&code))
+            .map_or(Class::Other, |(_, class)| *class)
+    }
+}
+
+impl fmt::Display for OsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        #[cfg(unix)]
+        write!(f, "{}", io::Error::from_raw_os_error(self.code))?;
+        #[cfg(windows)]
+        write!(
+            f,
+            "{}",
+            windows::core::Error::from_hresult(windows::core::HRESULT(self.code))
+        )?;
+
+        if !self.messages.is_empty() {
+            write!(f, ": {}", self.messages.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for OsError {}
+
+/// The variant an [`OsError`] is reported as.
+#[derive(Clone, Copy)]
+enum Class {
+    NotFound,
+    PermissionDenied,
+    Busy,
+    InvalidOptions,
+    UnknownFileSystem,
+    Unsupported,
+    Other,
 }
@@ -0,0 +1,563 @@
+//! Read and write `fstab` files.
+//!
+//! [`Fstab`] keeps every line of the file, including comments and blank lines, and
+//! the whitespace between fields, so editing a file and writing it back only changes
+//! the entries that were actually modified. Check [fstab(5)] for details about the
+//! format.
+//!
+//! [fstab(5)]: https://man7.org/linux/man-pages/man5/fstab.5.html
+
+use crate::common::unix::{escape, unescape};
+use crate::mount::{MountOptions, OptionString};
+use crate::Result;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{fmt, process};
+
+/// Tells apart the temporary files of threads writing at the same time.
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// The contents of a `fstab` file.
+///
+/// # Examples
+///
+/// Mount every entry of `/etc/fstab` that is not marked as `noauto`:
+///
+/// ```no_run
+/// use disket::fstab::Fstab;
+/// use std::error::Error;
+///
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     let fstab = Fstab::read("/etc/fstab")?;
+///
+///     for entry in fstab.entries() {
+///         if entry.options().contains("noauto") || entry.vfstype() == "swap" {
+///             continue;
+///         }
+///
+///         entry.mount_options().mount()?;
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+///
+/// Add an entry and write the file back:
+///
+/// ```no_run
+/// use disket::fstab::{Entry, Fstab, Spec};
+/// use std::error::Error;
+///
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     let mut fstab = Fstab::read("/etc/fstab")?;
+///
+///     let mut entry = Entry::new(Spec::Label("data".into()), "/data");
+///     entry
+///         .set_vfstype("ext4")
+///         .set_options("noatime,nofail".into())
+///         .set_passno(2);
+///
+///     fstab.push(entry);
+///     fstab.write("/etc/fstab")?;
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Fstab {
+    lines: Vec<Line>,
+    /// The last line of the file was not terminated by a newline.
+    missing_newline: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Line {
+    Entry(Entry),
+    Other(String),
+}
+
+impl Fstab {
+    /// Creates an empty `fstab`.
+    pub fn new() -> Self {
+        Fstab::default()
+    }
+
+    /// Reads and parses the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can not be read or one of its entries is malformed.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Atomically writes the contents of `self` to `path`.
+    ///
+    /// The contents are written to a temporary file in the same directory, which then
+    /// replaces `path`. Readers either see the old file or the new one, never a partial
+    /// write. Permissions of an existing file are kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the temporary file can not be written or renamed.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let name = path.file_name().unwrap_or(OsStr::new("fstab"));
+        let mut tmp_name = OsString::from(".");
+        tmp_name.push(name);
+        tmp_name.push(format!(
+            ".{}.{}.tmp",
+            process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let tmp = path.with_file_name(tmp_name);
+
+        let written = (|| -> io::Result<()> {
+            let mut file = OpenOptions::new().write(true).create_new(true).open(&tmp)?;
+
+            if let Ok(metadata) = fs::metadata(path) {
+                file.set_permissions(metadata.permissions())?;
+            }
+
+            file.write_all(self.to_string().as_bytes())?;
+            file.sync_all()?;
+            fs::rename(&tmp, path)?;
+
+            match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                Some(dir) => File::open(dir)?.sync_all(),
+                None => File::open(".")?.sync_all(),
+            }
+        })();
+
+        if written.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+
+        Ok(written?)
+    }
+
+    /// Returns an iterator over the entries.
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Entry(entry) => Some(entry),
+            Line::Other(_) => None,
+        })
+    }
+
+    /// Returns an iterator that allows modifying each entry.
+    pub fn entries_mut(&mut self) -> impl Iterator<Item = &mut Entry> {
+        self.lines.iter_mut().filter_map(|line| match line {
+            Line::Entry(entry) => Some(entry),
+            Line::Other(_) => None,
+        })
+    }
+
+    /// Returns the first entry mounted at `target`.
+    pub fn find<T: AsRef<Path>>(&self, target: T) -> Option<&Entry> {
+        self.entries().find(|e| e.target() == target.as_ref())
+    }
+
+    /// Returns the first entry mounted at `target` so it can be modified.
+    pub fn find_mut<T: AsRef<Path>>(&mut self, target: T) -> Option<&mut Entry> {
+        self.entries_mut().find(|e| e.target() == target.as_ref())
+    }
+
+    /// Appends an entry to the end of the file.
+    pub fn push(&mut self, entry: Entry) -> &mut Self {
+        self.lines.push(Line::Entry(entry));
+        self
+    }
+
+    /// Removes every entry mounted at `target` and returns them.
+    pub fn remove<T: AsRef<Path>>(&mut self, target: T) -> Vec<Entry> {
+        let mut removed = Vec::new();
+
+        self.lines.retain(|line| match line {
+            Line::Entry(entry) if entry.target() == target.as_ref() => {
+                removed.push(entry.clone());
+                false
+            }
+            _ => true,
+        });
+
+        removed
+    }
+
+    /// Keeps only the entries for which `f` returns `true`. Comments and blank lines are
+    /// always kept.
+    pub fn retain<F: FnMut(&Entry) -> bool>(&mut self, mut f: F) {
+        self.lines.retain(|line| match line {
+            Line::Entry(entry) => f(entry),
+            Line::Other(_) => true,
+        });
+    }
+}
+
+impl FromStr for Fstab {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let lines = s
+            .lines()
+            .enumerate()
+            .map(|(n, line)| {
+                let trimmed = line.trim_start();
+
+                if trimmed.is_empty() || trimmed.starts_with('#') {
+                    return Ok(Line::Other(line.to_string()));
+                }
+
+                Entry::parse(line).map(Line::Entry).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("malformed fstab entry at line {}: {line}", n + 1),
+                    )
+                    .into()
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Fstab {
+            lines,
+            missing_newline: !s.is_empty() && !s.ends_with('\n'),
+        })
+    }
+}
+
+impl fmt::Display for Fstab {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, line) in self.lines.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+
+            match line {
+                Line::Entry(entry) => write!(f, "{entry}")?,
+                Line::Other(line) => f.write_str(line)?,
+            }
+        }
+
+        if !self.lines.is_empty() && !self.missing_newline {
+            f.write_str("\n")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The block device or remote file system to be mounted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Spec {
+    /// A device node, or any other source understood by the file system, such as
+    /// `tmpfs` or `server:/export`.
+    Device(OsString),
+    /// `UUID=`, the file system UUID.
+    Uuid(String),
+    /// `LABEL=`, the file system label.
+    Label(String),
+    /// `PARTUUID=`, the GPT partition UUID.
+    PartUuid(String),
+    /// `PARTLABEL=`, the GPT partition name.
+    PartLabel(String),
+}
+
+impl FromStr for Spec {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let tag = |value: &str| {
+            let value = unescape(value.as_bytes()).to_string_lossy().into_owned();
+
+            match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+                Some(unquoted) => unquoted.to_string(),
+                None => value,
+            }
+        };
+
+        Ok(match s.split_once('=') {
+            Some(("UUID", value)) => Spec::Uuid(tag(value)),
+            Some(("LABEL", value)) => Spec::Label(tag(value)),
+            Some(("PARTUUID", value)) => Spec::PartUuid(tag(value)),
+            Some(("PARTLABEL", value)) => Spec::PartLabel(tag(value)),
+            _ => Spec::Device(unescape(s.as_bytes())),
+        })
+    }
+}
+
+impl Spec {
+    /// Returns the spec as expected by [`MountOptions::volume`].
+    fn volume(&self) -> OsString {
+        match self {
+            Spec::Device(device) => device.clone(),
+            Spec::Uuid(uuid) => format!("UUID={uuid}").into(),
+            Spec::Label(label) => format!("LABEL={label}").into(),
+            Spec::PartUuid(uuid) => format!("PARTUUID={uuid}").into(),
+            Spec::PartLabel(label) => format!("PARTLABEL={label}").into(),
+        }
+    }
+}
+
+impl fmt::Display for Spec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Spec::Device(device) => f.write_str(&escape(device)),
+            Spec::Uuid(uuid) => write!(f, "UUID={}", escape(uuid.as_ref())),
+            Spec::Label(label) => write!(f, "LABEL={}", escape(label.as_ref())),
+            Spec::PartUuid(uuid) => write!(f, "PARTUUID={}", escape(uuid.as_ref())),
+            Spec::PartLabel(label) => write!(f, "PARTLABEL={}", escape(label.as_ref())),
+        }
+    }
+}
+
+/// A single entry of a `fstab` file.
+///
+/// Setters return `&mut Self` so they can be chained.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    spec: Spec,
+    target: PathBuf,
+    vfstype: String,
+    options: OptionString,
+    freq: u32,
+    passno: u32,
+    /// Whitespace before each field.
+    separators: Vec<String>,
+    /// Whitespace after the last field.
+    trailing: String,
+    /// The line this entry was parsed from, until it is modified.
+    raw: Option<String>,
+}
+
+impl Entry {
+    /// Creates a new entry with `auto` as the file system type and default options.
+    pub fn new<T: AsRef<Path>>(spec: Spec, target: T) -> Self {
+        Self {
+            spec,
+            target: target.as_ref().to_path_buf(),
+            vfstype: String::from("auto"),
+            options: OptionString::new(),
+            freq: 0,
+            passno: 0,
+            separators: Vec::new(),
+            trailing: String::new(),
+            raw: None,
+        }
+    }
+
+    /// The block device or remote file system to be mounted.
+    pub fn spec(&self) -> &Spec {
+        &self.spec
+    }
+
+    /// The mount point.
+    pub fn target(&self) -> &Path {
+        &self.target
+    }
+
+    /// The file system type.
+    pub fn vfstype(&self) -> &str {
+        &self.vfstype
+    }
+
+    /// The mount options.
+    pub fn options(&self) -> &OptionString {
+        &self.options
+    }
+
+    /// Used by `dump(8)` to decide which file systems need to be dumped.
+    pub fn freq(&self) -> u32 {
+        self.freq
+    }
+
+    /// Used by `fsck(8)` to decide the order in which file systems are checked.
+    pub fn passno(&self) -> u32 {
+        self.passno
+    }
+
+    /// Sets the block device or remote file system to be mounted.
+    pub fn set_spec(&mut self, spec: Spec) -> &mut Self {
+        self.spec = spec;
+        self.raw = None;
+        self
+    }
+
+    /// Sets the mount point.
+    pub fn set_target<T: AsRef<Path>>(&mut self, target: T) -> &mut Self {
+        self.target = target.as_ref().to_path_buf();
+        self.raw = None;
+        self
+    }
+
+    /// Sets the file system type.
+    pub fn set_vfstype<T: Into<String>>(&mut self, vfstype: T) -> &mut Self {
+        self.vfstype = vfstype.into();
+        self.raw = None;
+        self
+    }
+
+    /// Sets the mount options.
+    pub fn set_options(&mut self, options: OptionString) -> &mut Self {
+        self.options = options;
+        self.raw = None;
+        self
+    }
+
+    /// Sets the `dump(8)` frequency.
+    pub fn set_freq(&mut self, freq: u32) -> &mut Self {
+        self.freq = freq;
+        self.raw = None;
+        self
+    }
+
+    /// Sets the `fsck(8)` pass number.
+    pub fn set_passno(&mut self, passno: u32) -> &mut Self {
+        self.passno = passno;
+        self.raw = None;
+        self
+    }
+
+    /// Creates [`MountOptions`] ready to mount this entry.
+    ///
+    /// The volume is set to `spec` as is, the mount point to `target`, and flags and data
+    /// come from `options`. The file system type is set from `vfstype`, unless it is `auto`.
+    ///
+    /// # Platform-specific behaviour
+    ///
+    /// The file system type is ignored on macOS and IOS.
+    pub fn mount_options(&self) -> MountOptions {
+        let mut options = MountOptions::new();
+
+        options
+            .volume(self.spec.volume())
+            .mount_point(&self.target)
+            .options(&self.options);
+
+        #[cfg(not(target_vendor = "apple"))]
+        if self.vfstype != "auto" {
+            options.inner.fs_type(Some(OsString::from(&self.vfstype)));
+        }
+
+        options
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = Vec::new();
+        let mut separators = Vec::new();
+        let mut rest = line;
+
+        let trailing = loop {
+            let start = rest.len() - rest.trim_start().len();
+            let (separator, field) = rest.split_at(start);
+
+            if field.is_empty() {
+                break separator.to_string();
+            }
+
+            let end = field.find(char::is_whitespace).unwrap_or(field.len());
+            separators.push(separator.to_string());
+            fields.push(&field[..end]);
+            rest = &field[end..];
+        };
+
+        if !(4..=6).contains(&fields.len()) {
+            return None;
+        }
+
+        Some(Self {
+            spec: fields[0].parse().ok()?,
+            target: unescape(fields[1].as_bytes()).into(),
+            vfstype: unescape(fields[2].as_bytes()).into_string().ok()?,
+            options: fields[3].into(),
+            freq: fields.get(4).map_or(Some(0), |f| f.parse().ok())?,
+            passno: fields.get(5).map_or(Some(0), |f| f.parse().ok())?,
+            separators,
+            trailing,
+            raw: Some(line.to_string()),
+        })
+    }
+}
+
+impl fmt::Display for Entry {
+    /// Formats the entry as a line of `fstab`.
+    ///
+    /// Entries that were not modified since they were parsed are formatted exactly as
+    /// they were read. Modified entries keep the original whitespace between fields.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(raw) = &self.raw {
+            return f.write_str(raw);
+        }
+
+        let fields = [
+            self.spec.to_string(),
+            escape(self.target.as_os_str()),
+            escape(self.vfstype.as_ref()),
+            self.options.to_string(),
+            self.freq.to_string(),
+            self.passno.to_string(),
+        ];
+
+        for (i, field) in fields.iter().enumerate() {
+            let separator = match self.separators.get(i) {
+                Some(separator) => separator.as_str(),
+                None if i == 0 => "",
+                None => "\t",
+            };
+
+            write!(f, "{separator}{field}")?;
+        }
+
+        f.write_str(&self.trailing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    const FSTAB: &str = "# /etc/fstab\n\
+        UUID=0a3407de-014b-458b-b5c1-848e92a327a3 /     ext4  defaults,noatime  0 1\n\
+        \n\
+        LABEL=My\\040Data  /mnt/my\\040data  auto  noauto,user  0  0\n\
+        tmpfs /tmp tmpfs mode=1777\t0 0   ";
+
+    #[test]
+    fn round_trip() {
+        for text in [FSTAB.to_string(), format!("{FSTAB}\n"), String::new()] {
+            assert_eq!(text.parse::<Fstab>().unwrap().to_string(), text);
+        }
+    }
+
+    #[test]
+    fn parse_entries() {
+        let fstab: Fstab = FSTAB.parse().unwrap();
+        let entry = fstab.find("/mnt/my data").unwrap();
+
+        assert_eq!(entry.spec(), &Spec::Label("My Data".into()));
+        assert!(entry.options().contains("noauto"));
+        assert_eq!(fstab.entries().count(), 3);
+        assert!("/dev/sda1 /".parse::<Fstab>().is_err());
+    }
+
+    #[test]
+    fn concurrent_writes() {
+        let dir = std::env::temp_dir().join(format!("disket-fstab-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("fstab");
+        let fstab: Fstab = FSTAB.parse().unwrap();
+
+        thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| fstab.write(&path).unwrap());
+            }
+        });
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), FSTAB);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -18,12 +18,20 @@
 //! - `device`: Get information about devices
 //! - `mount`: Mount and unmount file systems
 //! - `watcher`: Watch for device changes
+//! - `fstab`: Read and write `fstab` files
+//! - `loopdev`: Manage loop devices
 //! - `os`: Platform specific extensions and functions
 
 mod common;
 mod error;
 
-pub use error::{Error, Result};
+// Exposed through `device::partition_table` and `device::probe`, but also used by `mount`
+#[cfg_attr(not(feature = "device"), allow(dead_code, unused_imports))]
+mod partition_table;
+#[cfg_attr(not(feature = "device"), allow(dead_code, unused_imports))]
+mod probe;
+
+pub use error::{Error, OsError, Result};
 
 #[cfg(feature = "device")]
 pub mod device;
@@ -31,6 +39,12 @@ pub mod device;
 #[cfg(feature = "mount")]
 pub mod mount;
 
+#[cfg(all(feature = "fstab", unix))]
+pub mod fstab;
+
+#[cfg(all(feature = "loopdev", any(target_os = "linux", target_os = "android")))]
+pub mod loopdev;
+
 #[cfg(feature = "watch")]
 pub mod watch;
 
@@ -0,0 +1,607 @@
+//! Loop device management.
+//!
+//! A loop device makes a regular file available as a block device, which can then be
+//! mounted like any other volume. This module is built on `/dev/loop-control` and the
+//! `LOOP_*` ioctls. Check [loop(4)] for details.
+//!
+//! # Examples
+//!
+//! Attach a disk image, mount it and let the kernel release the loop device once
+//! it is unmounted:
+//!
+//! ```no_run
+//! # #[cfg(feature = "mount")]
+//! use disket::{loopdev::AttachOptions, mount::MountOptions};
+//! use std::error::Error;
+//!
+//! # #[cfg(feature = "mount")]
+//! fn main() -> Result<(), Box<dyn Error>> {
+//!     let device = AttachOptions::new()
+//!         .read_only(true)
+//!         .autoclear(true)
+//!         .attach("rootfs.img")?;
+//!
+//!     MountOptions::new()
+//!         .volume(device.path())
+//!         .mount_point("/mnt")
+//!         .read_only(true)
+//!         .mount()?;
+//!
+//!     Ok(())
+//! }
+//! # #[cfg(not(feature = "mount"))]
+//! # fn main() {}
+//! ```
+//!
+//! [loop(4)]: https://man7.org/linux/man-pages/man4/loop.4.html
+
+use crate::Result;
+use nix::errno::Errno;
+use nix::libc;
+use std::ffi::OsStr;
+use std::fs::{self, File, OpenOptions};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::{mem, thread, time::Duration};
+
+const LOOP_CONTROL: &str = "/dev/loop-control";
+
+const LO_FLAGS_READ_ONLY: u32 = 1;
+const LO_FLAGS_AUTOCLEAR: u32 = 4;
+const LO_FLAGS_PARTSCAN: u32 = 8;
+const LO_FLAGS_DIRECT_IO: u32 = 16;
+
+const LO_NAME_SIZE: usize = 64;
+const LO_KEY_SIZE: usize = 32;
+
+/// How many times a free device is requested before giving up when other processes
+/// keep taking it first.
+const ATTACH_ATTEMPTS: usize = 16;
+
+#[repr(C)]
+struct LoopInfo64 {
+    lo_device: u64,
+    lo_inode: u64,
+    lo_rdevice: u64,
+    lo_offset: u64,
+    lo_sizelimit: u64,
+    lo_number: u32,
+    lo_encrypt_type: u32,
+    lo_encrypt_key_size: u32,
+    lo_flags: u32,
+    lo_file_name: [u8; LO_NAME_SIZE],
+    lo_crypt_name: [u8; LO_NAME_SIZE],
+    lo_encrypt_key: [u8; LO_KEY_SIZE],
+    lo_init: [u64; 2],
+}
+
+#[repr(C)]
+struct LoopConfig {
+    fd: u32,
+    block_size: u32,
+    info: LoopInfo64,
+    reserved: [u64; 8],
+}
+
+mod ioctl {
+    use super::{LoopConfig, LoopInfo64};
+
+    nix::ioctl_write_int_bad!(loop_set_fd, 0x4c00);
+    nix::ioctl_none_bad!(loop_clr_fd, 0x4c01);
+    nix::ioctl_write_ptr_bad!(loop_set_status64, 0x4c04, LoopInfo64);
+    nix::ioctl_read_bad!(loop_get_status64, 0x4c05, LoopInfo64);
+    nix::ioctl_write_ptr_bad!(loop_configure, 0x4c0a, LoopConfig);
+    nix::ioctl_write_int_bad!(loop_ctl_add, 0x4c80);
+    nix::ioctl_write_int_bad!(loop_ctl_remove, 0x4c81);
+    nix::ioctl_none_bad!(loop_ctl_get_free, 0x4c82);
+}
+
+/// Handle to `/dev/loop-control`, used to allocate and free loop devices.
+#[derive(Debug)]
+pub struct LoopControl {
+    file: File,
+}
+
+impl LoopControl {
+    /// Opens `/dev/loop-control`.
+    pub fn open() -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_CLOEXEC)
+            .open(LOOP_CONTROL)?;
+
+        Ok(Self { file })
+    }
+
+    /// Returns the first loop device without a backing file, creating one if needed.
+    pub fn next_free(&self) -> Result<LoopDevice> {
+        let number = unsafe { ioctl::loop_ctl_get_free(self.file.as_raw_fd())? };
+        LoopDevice::open(device_path(number as u32))
+    }
+
+    /// Creates the loop device with the given number.
+    pub fn add(&self, number: u32) -> Result<LoopDevice> {
+        let number = unsafe { ioctl::loop_ctl_add(self.file.as_raw_fd(), number as libc::c_int)? };
+        LoopDevice::open(device_path(number as u32))
+    }
+
+    /// Removes the loop device with the given number. It must not have a backing file.
+    pub fn remove(&self, number: u32) -> Result<()> {
+        unsafe { ioctl::loop_ctl_remove(self.file.as_raw_fd(), number as libc::c_int)? };
+        Ok(())
+    }
+}
+
+/// A loop device.
+///
+/// Devices opened with [`LoopDevice::open`] or attached by [`AttachOptions::attach`] are
+/// kept open for as long as this value lives. A device attached with
+/// [`AttachOptions::autoclear`] is only released after it is dropped, and nothing else
+/// (e.g. a mount) uses the device. Devices returned by [`devices`] are only opened while
+/// they are used, so they never delay their release.
+#[derive(Debug)]
+pub struct LoopDevice {
+    file: Option<File>,
+    path: PathBuf,
+}
+
+impl LoopDevice {
+    /// Opens the loop device at `path`, such as `/dev/loop0`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+
+        Ok(Self {
+            file: Some(open_device(path)?),
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Path of the device node.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of the device, such as `0` for `/dev/loop0`.
+    pub fn number(&self) -> Result<u32> {
+        Ok(self.status()?.number)
+    }
+
+    /// Returns the status of the device.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ENXIO` if the device has no backing file.
+    pub fn status(&self) -> Result<Status> {
+        let mut info: LoopInfo64 = unsafe { mem::zeroed() };
+        self.with_file(|file| {
+            unsafe { ioctl::loop_get_status64(file.as_raw_fd(), &mut info)? };
+            Ok(())
+        })?;
+
+        let name = self.path.file_name().unwrap_or_default();
+        let sysfs = Path::new("/sys/block").join(name).join("loop/backing_file");
+        let backing_file = match fs::read(sysfs) {
+            Ok(path) => {
+                let path = path.strip_suffix(b"\n").unwrap_or(&path);
+                PathBuf::from(OsStr::from_bytes(path))
+            }
+            Err(_) => {
+                let end = info.lo_file_name.iter().position(|&b| b == 0);
+                let name = &info.lo_file_name[..end.unwrap_or(LO_NAME_SIZE)];
+                PathBuf::from(OsStr::from_bytes(name))
+            }
+        };
+
+        Ok(Status {
+            number: info.lo_number,
+            backing_file,
+            backing_device: info.lo_device,
+            backing_inode: info.lo_inode,
+            offset: info.lo_offset,
+            size_limit: info.lo_sizelimit,
+            flags: info.lo_flags,
+        })
+    }
+
+    /// Detaches the backing file from the device.
+    ///
+    /// If the device is still in use, e.g. mounted, it is released as soon as it is no
+    /// longer used instead.
+    pub fn detach(&self) -> Result<()> {
+        self.with_file(|file| {
+            unsafe { ioctl::loop_clr_fd(file.as_raw_fd())? };
+            Ok(())
+        })
+        .map_err(|e| e.context("detach", [self.path()]))
+    }
+
+    /// Calls `f` with the open device, opening it just for the call if needed.
+    fn with_file<T>(&self, f: impl FnOnce(&File) -> Result<T>) -> Result<T> {
+        match &self.file {
+            Some(file) => f(file),
+            None => f(&open_device(&self.path)?),
+        }
+    }
+
+    fn configure(&self, backing: &File, path: &Path, options: &AttachOptions) -> Result<()> {
+        self.with_file(|file| self.configure_file(file, backing, path, options))
+    }
+
+    fn configure_file(
+        &self,
+        file: &File,
+        backing: &File,
+        path: &Path,
+        options: &AttachOptions,
+    ) -> Result<()> {
+        let mut config: LoopConfig = unsafe { mem::zeroed() };
+        config.fd = backing.as_raw_fd() as u32;
+        config.block_size = options.block_size;
+        config.info = options.info(path);
+
+        let configured = unsafe { ioctl::loop_configure(file.as_raw_fd(), &config) };
+
+        match configured {
+            Ok(_) => Ok(()),
+            // Kernels older than 5.8 don't know `LOOP_CONFIGURE`
+            Err(Errno::EINVAL | Errno::ENOTTY) if options.block_size == 0 => {
+                unsafe { ioctl::loop_set_fd(file.as_raw_fd(), backing.as_raw_fd())? };
+
+                let info = options.info(path);
+                let status = unsafe { ioctl::loop_set_status64(file.as_raw_fd(), &info) };
+
+                if let Err(e) = status {
+                    let _ = self.detach();
+                    return Err(e.into());
+                }
+
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Status of an attached loop device.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Status {
+    number: u32,
+    backing_file: PathBuf,
+    backing_device: u64,
+    backing_inode: u64,
+    offset: u64,
+    size_limit: u64,
+    flags: u32,
+}
+
+impl Status {
+    /// Number of the device, such as `0` for `/dev/loop0`.
+    pub fn number(&self) -> u32 {
+        self.number
+    }
+
+    /// Path of the backing file, as given when it was attached.
+    pub fn backing_file(&self) -> &Path {
+        &self.backing_file
+    }
+
+    /// Device number of the file system holding the backing file.
+    pub fn backing_device(&self) -> u64 {
+        self.backing_device
+    }
+
+    /// Inode number of the backing file.
+    pub fn backing_inode(&self) -> u64 {
+        self.backing_inode
+    }
+
+    /// Offset in bytes where the data starts in the backing file.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Maximum size in bytes of the data, or `0` for the whole file.
+    pub fn size_limit(&self) -> u64 {
+        self.size_limit
+    }
+
+    /// Returns `true` if the device is read-only.
+    pub fn read_only(&self) -> bool {
+        self.flags & LO_FLAGS_READ_ONLY != 0
+    }
+
+    /// Returns `true` if the device is released once it is no longer used.
+    pub fn autoclear(&self) -> bool {
+        self.flags & LO_FLAGS_AUTOCLEAR != 0
+    }
+
+    /// Returns `true` if the kernel scans the device for partitions.
+    pub fn part_scan(&self) -> bool {
+        self.flags & LO_FLAGS_PARTSCAN != 0
+    }
+
+    /// Returns `true` if the backing file is accessed with direct I/O.
+    pub fn direct_io(&self) -> bool {
+        self.flags & LO_FLAGS_DIRECT_IO != 0
+    }
+}
+
+/// Options used to configure how a file is attached to a loop device.
+///
+/// Start by calling `new`, chain calls to set every option and then call `attach`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AttachOptions {
+    offset: u64,
+    size_limit: u64,
+    block_size: u32,
+    flags: u32,
+    device: Option<PathBuf>,
+}
+
+impl AttachOptions {
+    /// Creates a new set of options with default values.
+    pub fn new() -> Self {
+        AttachOptions::default()
+    }
+
+    /// Sets the offset in bytes where the data starts in the file.
+    pub fn offset(&mut self, offset: u64) -> &mut Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Sets the maximum size in bytes of the data. `0` means up to the end of the file.
+    pub fn size_limit(&mut self, size_limit: u64) -> &mut Self {
+        self.size_limit = size_limit;
+        self
+    }
+
+    /// Sets the logical block size of the device. `0` means the kernel default.
+    ///
+    /// Requires Linux 5.8 or newer.
+    pub fn block_size(&mut self, block_size: u32) -> &mut Self {
+        self.block_size = block_size;
+        self
+    }
+
+    /// Attaches the file as read-only.
+    pub fn read_only(&mut self, read_only: bool) -> &mut Self {
+        self.set_flag(LO_FLAGS_READ_ONLY, read_only)
+    }
+
+    /// Releases the device once it is no longer used, e.g. when the file system on it is
+    /// unmounted.
+    pub fn autoclear(&mut self, autoclear: bool) -> &mut Self {
+        self.set_flag(LO_FLAGS_AUTOCLEAR, autoclear)
+    }
+
+    /// Makes the kernel scan the device for partitions, available at `/dev/loopNpM`.
+    pub fn part_scan(&mut self, part_scan: bool) -> &mut Self {
+        self.set_flag(LO_FLAGS_PARTSCAN, part_scan)
+    }
+
+    /// Accesses the file with direct I/O, bypassing the page cache.
+    pub fn direct_io(&mut self, direct_io: bool) -> &mut Self {
+        self.set_flag(LO_FLAGS_DIRECT_IO, direct_io)
+    }
+
+    /// Sets the loop device to use, such as `/dev/loop3`. By default the first free
+    /// device is used.
+    pub fn device<P: AsRef<Path>>(&mut self, device: Option<P>) -> &mut Self {
+        self.device = device.map(|d| d.as_ref().to_path_buf());
+        self
+    }
+
+    /// Attaches `file` to a loop device with the options specified by `self`.
+    ///
+    /// `LOOP_CONFIGURE` is used to set up the device at once. On kernels older than
+    /// 5.8, it falls back to `LOOP_SET_FD` and `LOOP_SET_STATUS64`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`Error::Busy`](crate::Error::Busy) error if a specific device was
+    /// requested and it already has a backing file.
+    pub fn attach<P: AsRef<Path>>(&self, file: P) -> Result<LoopDevice> {
+        let file = file.as_ref();
+        self.attach_file(file)
+            .map_err(|e| e.context("attach", [file]))
+    }
+
+    fn attach_file(&self, file: &Path) -> Result<LoopDevice> {
+        let file = fs::canonicalize(file)?;
+        let backing = OpenOptions::new()
+            .read(true)
+            .write(self.flags & LO_FLAGS_READ_ONLY == 0)
+            .custom_flags(libc::O_CLOEXEC)
+            .open(&file)?;
+
+        if let Some(path) = &self.device {
+            let device = LoopDevice::open(path)?;
+            device.configure(&backing, &file, self)?;
+            return Ok(device);
+        }
+
+        let control = LoopControl::open()?;
+
+        for _ in 0..ATTACH_ATTEMPTS {
+            let device = control.next_free()?;
+
+            match device.configure(&backing, &file, self) {
+                // Someone else took the device first
+                Err(crate::Error::Busy { .. }) => {
+                    thread::sleep(Duration::from_millis(10));
+                    continue;
+                }
+                configured => return configured.map(|_| device),
+            }
+        }
+
+        Err(Errno::EBUSY.into())
+    }
+
+    fn set_flag(&mut self, flag: u32, enabled: bool) -> &mut Self {
+        if enabled {
+            self.flags |= flag;
+        } else {
+            self.flags &= !flag;
+        }
+
+        self
+    }
+
+    fn info(&self, file: &Path) -> LoopInfo64 {
+        let mut info: LoopInfo64 = unsafe { mem::zeroed() };
+        info.lo_offset = self.offset;
+        info.lo_sizelimit = self.size_limit;
+        info.lo_flags = self.flags;
+
+        // Truncated like losetup does, keeping the last byte for the terminating NUL
+        let name = file.as_os_str().as_bytes();
+        let len = name.len().min(LO_NAME_SIZE - 1);
+        info.lo_file_name[..len].copy_from_slice(&name[..len]);
+
+        info
+    }
+}
+
+/// Attaches `file` to the first free loop device.
+///
+/// This function is interchangeable with manually calling `attach` on [`AttachOptions`]
+/// with default options.
+pub fn attach<P: AsRef<Path>>(file: P) -> Result<LoopDevice> {
+    AttachOptions::new().attach(file)
+}
+
+/// Detaches the backing file from the loop device at `device`.
+pub fn detach<P: AsRef<Path>>(device: P) -> Result<()> {
+    LoopDevice::open(device)?.detach()
+}
+
+/// Returns every loop device with a backing file.
+///
+/// The devices are not opened until they are used, so one that is detached in the
+/// meantime returns an error then.
+pub fn devices() -> Result<Vec<LoopDevice>> {
+    let mut devices = Vec::new();
+
+    for entry in fs::read_dir("/sys/block")? {
+        let entry = entry?;
+        let name = entry.file_name();
+
+        if !name.as_bytes().starts_with(b"loop") || !entry.path().join("loop/backing_file").exists()
+        {
+            continue;
+        }
+
+        devices.push(LoopDevice {
+            file: None,
+            path: Path::new("/dev").join(name),
+        });
+    }
+
+    // The device may be released by the time it is sorted
+    devices.sort_by_key(|d| fs::metadata(&d.path).map(|m| m.rdev()).unwrap_or_default());
+
+    Ok(devices)
+}
+
+/// Opens a loop device, read-only if it can't be written to.
+fn open_device(path: &Path) -> Result<File> {
+    let open = |write| {
+        OpenOptions::new()
+            .read(true)
+            .write(write)
+            .custom_flags(libc::O_CLOEXEC)
+            .open(path)
+    };
+
+    match open(true) {
+        Err(e) if e.raw_os_error() == Some(libc::EROFS) => Ok(open(false)?),
+        file => Ok(file?),
+    }
+}
+
+fn device_path(number: u32) -> PathBuf {
+    PathBuf::from(format!("/dev/loop{number}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Loop devices can only be set up by root, with `/dev/loop-control` available.
+    fn can_attach() -> bool {
+        let root = unsafe { libc::geteuid() } == 0;
+        root && Path::new(LOOP_CONTROL).exists()
+    }
+
+    /// Detaches the device and removes its backing file, even when the test fails.
+    struct Scratch {
+        path: PathBuf,
+        device: Option<LoopDevice>,
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            if let Some(device) = &self.device {
+                let _ = device.detach();
+            }
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+
+    #[test]
+    fn attach_sparse_file() {
+        if !can_attach() {
+            return;
+        }
+
+        let path = std::env::temp_dir().join(format!("disket-loop-{}.img", std::process::id()));
+        File::create(&path).unwrap().set_len(64 << 20).unwrap();
+        let mut scratch = Scratch {
+            path: fs::canonicalize(&path).unwrap(),
+            device: None,
+        };
+        let path = scratch.path.clone();
+
+        let device = scratch.device.insert(
+            AttachOptions::new()
+                .offset(1 << 20)
+                .size_limit(16 << 20)
+                .read_only(true)
+                .attach(&path)
+                .unwrap(),
+        );
+
+        let status = device.status().unwrap();
+        assert_eq!(status.backing_file(), path);
+        assert_eq!(status.offset(), 1 << 20);
+        assert_eq!(status.size_limit(), 16 << 20);
+        assert!(status.read_only());
+        assert!(!status.autoclear());
+
+        // Read the name the kernel keeps too, used when sysfs is not available
+        let mut info: LoopInfo64 = unsafe { mem::zeroed() };
+        device
+            .with_file(|file| Ok(unsafe { ioctl::loop_get_status64(file.as_raw_fd(), &mut info)? }))
+            .unwrap();
+        let bytes = path.as_os_str().as_bytes();
+        let len = bytes.len().min(LO_NAME_SIZE - 1);
+        assert_eq!(&info.lo_file_name[..len], &bytes[..len]);
+        assert_eq!(info.lo_file_name[len], 0);
+
+        let name = device.path().file_name().unwrap();
+        let size = fs::read_to_string(Path::new("/sys/block").join(name).join("size")).unwrap();
+        assert_eq!(size.trim(), ((16 << 20) / 512).to_string());
+
+        let listed = devices().unwrap();
+        let found = listed.iter().find(|d| d.path() == device.path()).unwrap();
+        assert_eq!(found.status().unwrap(), status);
+
+        device.detach().unwrap();
+        assert!(device.status().is_err());
+    }
+}
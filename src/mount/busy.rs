@@ -0,0 +1,261 @@
+use crate::Result;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Path, PathBuf};
+
+/// How a process refers to a file on a file system.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Reference {
+    /// The current working directory of the process.
+    WorkingDirectory,
+    /// The root directory of the process, as changed by `chroot`.
+    Root,
+    /// The executable the process is running.
+    Executable,
+    /// A file descriptor open by the process.
+    OpenFile,
+    /// A file mapped into the memory of the process, such as a shared library.
+    MappedFile,
+}
+
+/// A process keeping a file system busy. Returned by [`busy_users`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BusyUser {
+    pid: u32,
+    command: OsString,
+    reference: Reference,
+    path: PathBuf,
+}
+
+impl BusyUser {
+    /// Identifier of the process.
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Name of the command run by the process, as found in `/proc/<pid>/comm`.
+    pub fn command(&self) -> &OsStr {
+        &self.command
+    }
+
+    /// How the process refers to the file.
+    pub fn reference(&self) -> Reference {
+        self.reference
+    }
+
+    /// Path of the file, as seen by the process.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Returns every process holding files on the file system at `path`, similar to
+/// `fuser -m`.
+///
+/// `path` can be any file on the file system, usually its mount point, or the block device
+/// it is mounted from. A process is reported once for each reference it holds, so the same
+/// process may appear more than once.
+///
+/// Processes that can't be inspected, usually because they belong to another user and the
+/// caller is not privileged, are skipped.
+///
+/// # Platform-specific behaviour
+///
+/// This function is only available on Linux and Android, where it scans the `cwd`, `root`,
+/// `exe`, `fd` and `maps` entries of every process in `/proc`.
+///
+/// # Errors
+///
+/// Returns an error if `path` or `/proc` can't be read.
+///
+/// # Examples
+///
+/// Explain why a file system can't be unmounted:
+///
+/// ```no_run
+/// use disket::mount;
+/// use std::error::Error;
+///
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     if let Err(e) = mount::unmount("/mnt") {
+///         for user in mount::busy_users("/mnt")? {
+///             eprintln!(
+///                 "{} ({:?}) holds {}",
+///                 user.pid(),
+///                 user.command(),
+///                 user.path().display()
+///             );
+///         }
+///
+///         return Err(e.into());
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub fn busy_users<T: AsRef<Path>>(path: T) -> Result<Vec<BusyUser>> {
+    let metadata = fs::metadata(path)?;
+    let device = if metadata.file_type().is_block_device() {
+        metadata.rdev()
+    } else {
+        metadata.dev()
+    };
+
+    let mut users = Vec::new();
+
+    for entry in fs::read_dir("/proc")? {
+        let entry = entry?;
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse().ok())
+        else {
+            continue;
+        };
+
+        // The process may exit at any time, so every error from here on is ignored
+        let _ = scan(pid, &entry.path(), device, &mut users);
+    }
+
+    Ok(users)
+}
+
+fn scan(pid: u32, dir: &Path, device: u64, users: &mut Vec<BusyUser>) -> io::Result<()> {
+    let command = fs::read(dir.join("comm"))?;
+    let command = OsStr::from_bytes(command.strip_suffix(b"\n").unwrap_or(&command));
+    let mut push = |reference, path| {
+        users.push(BusyUser {
+            pid,
+            command: command.to_os_string(),
+            reference,
+            path,
+        })
+    };
+
+    let links = [
+        ("cwd", Reference::WorkingDirectory),
+        ("root", Reference::Root),
+        ("exe", Reference::Executable),
+    ];
+
+    for (name, reference) in links {
+        let link = dir.join(name);
+
+        if fs::metadata(&link).is_ok_and(|m| m.dev() == device) {
+            push(reference, fs::read_link(&link).unwrap_or(link));
+        }
+    }
+
+    if let Ok(fds) = fs::read_dir(dir.join("fd")) {
+        for fd in fds.flatten() {
+            let link = fd.path();
+
+            if fs::metadata(&link).is_ok_and(|m| m.dev() == device) {
+                push(Reference::OpenFile, fs::read_link(&link).unwrap_or(link));
+            }
+        }
+    }
+
+    let maps = fs::read(dir.join("maps"))?;
+
+    for path in mapped_files(&maps, device) {
+        push(Reference::MappedFile, OsStr::from_bytes(path).into());
+    }
+
+    Ok(())
+}
+
+/// Returns the files mapped from `device` in `maps`, the contents of `/proc/<pid>/maps`,
+/// each once.
+fn mapped_files(maps: &[u8], device: u64) -> Vec<&[u8]> {
+    let mut mapped: Vec<&[u8]> = Vec::new();
+
+    for line in maps.split(|&b| b == b'\n') {
+        // address perms offset dev inode path
+        let mut fields = line.splitn(6, |&b| b == b' ');
+        let Some(dev) = fields.nth(3) else {
+            continue;
+        };
+
+        let path = fields.nth(1).unwrap_or_default();
+        let path = &path[path.iter().take_while(|&&b| b == b' ').count()..];
+
+        if !path.is_empty() && same_device(dev, device) && !mapped.contains(&path) {
+            mapped.push(path);
+        }
+    }
+
+    mapped
+}
+
+/// Returns `true` if `field`, a device in the `major:minor` hexadecimal form used in
+/// `/proc/<pid>/maps`, is `device`.
+fn same_device(field: &[u8], device: u64) -> bool {
+    let Some((major, minor)) = std::str::from_utf8(field)
+        .ok()
+        .and_then(|field| field.split_once(':'))
+    else {
+        return false;
+    };
+
+    let (Ok(major), Ok(minor)) = (
+        u64::from_str_radix(major, 16),
+        u64::from_str_radix(minor, 16),
+    ) else {
+        return false;
+    };
+
+    // Same encoding as `makedev` in glibc and bionic
+    let encoded = ((major & 0xfffff000) << 32)
+        | ((major & 0xfff) << 8)
+        | ((minor & 0xffffff00) << 12)
+        | (minor & 0xff);
+
+    encoded == device
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nix::libc;
+
+    const MAPS: &[u8] = b"\
+55d4c2a00000-55d4c2a28000 r--p 00000000 08:01 1835019                    /usr/bin/bash
+55d4c2a28000-55d4c2b05000 r-xp 00028000 08:01 1835019                    /usr/bin/bash
+7f1e3c000000-7f1e3c021000 rw-p 00000000 00:00 0 
+7f1e3c400000-7f1e3c428000 r--p 00000000 103:02 2359305                   /srv/data/lib/libfoo.so
+7f1e3c600000-7f1e3c601000 rw-s 00000000 103:02 2359311                   /srv/data/shared memory
+7ffd1a3f0000-7ffd1a411000 rw-p 00000000 00:00 0                          [stack]
+";
+
+    #[test]
+    fn devices() {
+        assert!(same_device(b"08:01", libc::makedev(8, 1)));
+        assert!(same_device(b"103:02", libc::makedev(259, 2)));
+        assert!(same_device(b"fd:1a", libc::makedev(253, 26)));
+        assert!(same_device(b"1000:100", libc::makedev(4096, 256)));
+        assert!(!same_device(b"08:01", libc::makedev(8, 2)));
+        assert!(!same_device(b"00:00", libc::makedev(8, 1)));
+        assert!(!same_device(b"08", libc::makedev(8, 0)));
+        assert!(!same_device(b"xy:01", libc::makedev(8, 1)));
+    }
+
+    #[test]
+    fn maps() {
+        let mapped = mapped_files(MAPS, libc::makedev(8, 1));
+        assert_eq!(mapped, [&b"/usr/bin/bash"[..]]);
+
+        let mapped = mapped_files(MAPS, libc::makedev(259, 2));
+        assert_eq!(
+            mapped,
+            [&b"/srv/data/lib/libfoo.so"[..], b"/srv/data/shared memory"]
+        );
+
+        // Anonymous mappings have no path, unlike the stack
+        assert_eq!(mapped_files(MAPS, 0), [&b"[stack]"[..]]);
+        assert!(mapped_files(b"", libc::makedev(8, 1)).is_empty());
+    }
+}
@@ -0,0 +1,141 @@
+use super::UnmountOptions;
+use crate::Result;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// What to do when unmounting fails because the file system is busy.
+///
+/// Used by [`super::UnmountOptions::fallback`] and [`MountGuard::fallback`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Fallback {
+    /// Give up and leave the file system mounted.
+    #[default]
+    None,
+    /// Detach the file system right away and clean it up once it is no longer busy.
+    ///
+    /// Only supported on Linux and Android.
+    Lazy,
+    /// Force the file system to be unmounted, even if it is busy.
+    ///
+    /// Not supported on Windows.
+    Force,
+}
+
+/// A mounted file system that is unmounted when dropped.
+///
+/// This is returned by [`super::MountOptions::mount_scoped`] and
+/// [`super::MountOptions::mount_temporary`]. Errors when unmounting on drop are ignored;
+/// call [`MountGuard::unmount`] to handle them.
+///
+/// # Examples
+///
+/// ```no_run
+/// use disket::mount::{Fallback, MountOptions};
+/// use std::error::Error;
+///
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     let mut guard = MountOptions::new()
+///         .volume("/dev/sdb1")
+///         .mount_temporary()?;
+///
+///     guard.fallback(Fallback::Lazy);
+///     println!("{:?}", std::fs::read_dir(guard.mount_point())?.count());
+///
+///     Ok(()) // Unmounted here, even if something above fails
+/// }
+/// ```
+#[derive(Debug)]
+pub struct MountGuard {
+    mount_point: PathBuf,
+    fallback: Fallback,
+    temporary: bool,
+}
+
+impl MountGuard {
+    pub(crate) fn new(mount_point: PathBuf, temporary: bool) -> Self {
+        Self {
+            mount_point,
+            fallback: Fallback::None,
+            temporary,
+        }
+    }
+
+    /// Returns the path where the file system is mounted.
+    pub fn mount_point(&self) -> &Path {
+        &self.mount_point
+    }
+
+    /// Sets what to do when unmounting fails. Defaults to [`Fallback::None`].
+    pub fn fallback(&mut self, fallback: Fallback) -> &mut Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Unmounts the file system, returning any error.
+    ///
+    /// The fallback is tried if the file system is busy, and its error is returned if it also
+    /// fails. A temporary mount point is removed once the file system is unmounted.
+    pub fn unmount(self) -> Result<()> {
+        let mut guard = mem::ManuallyDrop::new(self);
+        let result = guard.release();
+        drop(mem::take(&mut guard.mount_point));
+        result
+    }
+
+    /// Leaves the file system mounted and returns its mount point, which is then up to the
+    /// caller to unmount.
+    ///
+    /// A temporary mount point is not removed either.
+    pub fn into_inner(self) -> PathBuf {
+        let mut guard = mem::ManuallyDrop::new(self);
+        mem::take(&mut guard.mount_point)
+    }
+
+    /// Leaves the file system mounted for the rest of the program and returns its mount
+    /// point.
+    ///
+    /// This is [`MountGuard::into_inner`] for mounts that are never unmounted, such as
+    /// those of a short-lived tool, where a `'static` path is more convenient.
+    pub fn leak(self) -> &'static Path {
+        Box::leak(self.into_inner().into_boxed_path())
+    }
+
+    fn release(&mut self) -> Result<()> {
+        UnmountOptions::new()
+            .mount_point(&self.mount_point)
+            .fallback(self.fallback)
+            .unmount()?;
+
+        if self.temporary {
+            fs::remove_dir(&self.mount_point)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for MountGuard {
+    fn drop(&mut self) {
+        let _ = self.release();
+    }
+}
+
+/// Creates an empty directory to be used as a temporary mount point.
+pub(crate) fn temporary_dir() -> Result<PathBuf> {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    loop {
+        let count = COUNT.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("disket-{}-{count}", process::id()));
+
+        match fs::create_dir(&path) {
+            Ok(()) => return Ok(path),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use super::MountOptions;
+use crate::loopdev::AttachOptions;
+use crate::partition_table::{self, PartitionTable};
+use crate::probe::{self, Usage};
+use crate::{Error, Result};
+use std::ffi::OsString;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// A partition inside a disk image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Partition {
+    /// The partition with the given number, starting at 1. Numbers follow the kernel, so
+    /// logical partitions of an MBR partition table start at 5.
+    Index(u32),
+    /// The GPT partition with the given name.
+    Name(String),
+}
+
+/// Mounts a disk image file through a loop device.
+///
+/// This is the equivalent of `mount -o loop`. The image is attached to the first free
+/// loop device with autoclear set, so the kernel releases the device once the file system
+/// is unmounted, or right away if mounting fails.
+///
+/// The whole image is mounted by default. Use `partition` to mount a single partition of a
+/// GPT or MBR partitioned image instead. The partition table is read from the image itself,
+/// and the loop device only covers the selected partition.
+///
+/// If no file system type is set in the [`MountOptions`], it is detected from the
+/// superblock the same way `device::probe` does.
+///
+/// # Examples
+///
+/// Mount the partition called `root` of a GPT partitioned image as read-only:
+///
+/// ```no_run
+/// use disket::mount::{ImageMount, MountOptions, Partition};
+/// use std::error::Error;
+///
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     let mut options = MountOptions::new();
+///     options.mount_point("/mnt").read_only(true);
+///
+///     let device = ImageMount::new("rootfs.img")
+///         .partition(Partition::Name("root".into()))
+///         .mount(&options)?;
+///
+///     println!("mounted at /mnt using {}", device.display());
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImageMount {
+    image: PathBuf,
+    partition: Option<Partition>,
+}
+
+impl ImageMount {
+    /// Creates a new image mount for the file at `image`.
+    pub fn new<P: AsRef<Path>>(image: P) -> Self {
+        Self {
+            image: image.as_ref().to_path_buf(),
+            partition: None,
+        }
+    }
+
+    /// Selects the partition to be mounted.
+    pub fn partition(&mut self, partition: Partition) -> &mut Self {
+        self.partition = Some(partition);
+        self
+    }
+
+    /// Mounts the image with `options`. The volume set in `options` is ignored.
+    ///
+    /// The loop device is attached read-only if `options` is read-only. Returns the path
+    /// of the loop device used.
+    ///
+    /// # Errors
+    ///
+    /// Besides any error from [`AttachOptions::attach`] and [`MountOptions::mount`],
+    /// an [`Error::NotFound`] error with the path of the image is returned if the
+    /// partition is not found.
+    pub fn mount(&self, options: &MountOptions) -> Result<PathBuf> {
+        let mut attach = AttachOptions::new();
+        attach
+            .read_only(options.inner.is_read_only())
+            .autoclear(true);
+
+        if let Some(partition) = &self.partition {
+            let found = self.find(partition)?;
+            attach.offset(found.offset()).size_limit(found.size());
+        }
+
+        let device = attach.attach(&self.image)?;
+        let mut options = options.clone();
+        options.volume(device.path());
+
+        if !options.inner.has_fs_type() {
+            let probe = probe::probe(&mut File::open(device.path())?)?
+                .filter(|probe| probe.usage() == Usage::FileSystem);
+            options
+                .inner
+                .fs_type(probe.map(|probe| OsString::from(probe.fs_type())));
+        }
+
+        options.mount()?;
+
+        Ok(device.path().to_path_buf())
+    }
+
+    fn find(&self, partition: &Partition) -> Result<partition_table::Partition> {
+        let operation = "find partition in";
+        let table = File::open(&self.image)
+            .map_err(Error::from)
+            .and_then(|mut image| PartitionTable::read(&mut image))
+            .map_err(|e| e.context(operation, [&self.image]))?;
+
+        table
+            .iter()
+            .flat_map(|table| table.partitions())
+            .find(|found| match partition {
+                Partition::Index(index) => found.number() == *index,
+                Partition::Name(name) => found.name() == Some(name),
+            })
+            .cloned()
+            .ok_or_else(|| Error::NotFound {
+                operation,
+                paths: vec![self.image.clone()],
+                source: None,
+            })
+    }
+}
@@ -13,8 +13,41 @@ pub(crate) mod unix;
 #[cfg(unix)]
 use unix as sys;
 
-use crate::Result;
-use std::ffi::OsStr;
+mod guard;
+pub use guard::{Fallback, MountGuard};
+
+#[cfg(unix)]
+pub(crate) mod options;
+#[cfg(unix)]
+pub use options::OptionString;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod table;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use table::{mounts, MountEntry};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod busy;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use busy::{busy_users, BusyUser, Reference};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod propagation;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use propagation::{set_propagation, Propagation};
+
+#[cfg(all(feature = "loopdev", any(target_os = "linux", target_os = "android")))]
+mod image;
+#[cfg(all(feature = "loopdev", any(target_os = "linux", target_os = "android")))]
+pub use image::{ImageMount, Partition};
+
+use crate::error::Operation;
+use crate::{Error, Result};
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
 
 /// Options used to configure how the volume is mounted.
 ///
@@ -23,8 +56,10 @@ use std::ffi::OsStr;
 /// You should chain calls to set every option and then call `mount`. Doing
 /// otherwise will probably return an error.
 ///
-/// Only `volume` and `mount_point` are available in all platforms. For platform-specific
-/// options use an extension trait such as `disket::os::mount::linux::MountOptionsExt`.
+/// `volume`, `mount_point` and the common mount flags (`read_only`, `no_exec`...) are available
+/// in all platforms. Flags without an equivalent on the current platform are ignored. For
+/// platform-specific options use an extension trait such as
+/// `disket::os::mount::linux::MountOptionsExt`.
 ///
 /// # Examples
 ///
@@ -32,6 +67,7 @@ use std::ffi::OsStr;
 ///
 /// ```no_run
 /// use disket::mount::MountOptions;
+/// use std::error::Error;
 ///
 /// fn main() -> Result<(), Box<dyn Error>> {
 ///     MountOptions::new()
@@ -42,20 +78,43 @@ use std::ffi::OsStr;
 ///     Ok(())
 /// }
 /// ```
+/// Mount a read-only volume where nothing can be executed:
+///
+/// ```no_run
+/// use disket::mount::MountOptions;
+/// use std::error::Error;
+///
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     MountOptions::new()
+///         .volume("/dev/sda2")
+///         .mount_point("/mnt")
+///         .read_only(true)
+///         .no_exec(true)
+///         .mount()?;
+///
+///     Ok(())
+/// }
+/// ```
+///
 /// Mount with platform-specific options:
 ///
 /// ```no_run
+/// # #[cfg(target_os = "linux")]
 /// use disket::{mount::MountOptions, os::mount::linux::MountOptionsExt};
+/// use std::error::Error;
 ///
+/// # #[cfg(target_os = "linux")]
 /// fn main() -> Result<(), Box<dyn Error>> {
 ///     MountOptions::new()
 ///         .volume("/dev/sda2")
 ///         .mount_point("/mnt")
-///         .fstype("ext4")
+///         .fs_type(Some("ext4"))
 ///         .mount()?;
 ///
 ///     Ok(())
 /// }
+/// # #[cfg(not(target_os = "linux"))]
+/// # fn main() {}
 /// ```
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct MountOptions {
@@ -77,6 +136,13 @@ impl MountOptions {
     }
 
     /// Sets the volume identifier to be mounted.
+    ///
+    /// # Platform-specific behaviour
+    ///
+    /// On Linux and Android, the volume can also be given as `UUID=`, `LABEL=`, `PARTUUID=`
+    /// or `PARTLABEL=`, just like in `fstab(5)`. It is resolved to a device node right before
+    /// mounting, using the links at `/dev/disk/by-*` or, when they are missing, by probing
+    /// the superblock of every block device, as `device::probe` does.
     pub fn volume<T: AsRef<OsStr>>(&mut self, volume: T) -> &mut Self {
         self.inner.volume(volume.as_ref().to_os_string());
         self
@@ -88,11 +154,137 @@ impl MountOptions {
         self
     }
 
+    /// Mounts the file system as read-only.
+    pub fn read_only(&mut self, read_only: bool) -> &mut Self {
+        self.inner.read_only(read_only);
+        self
+    }
+
+    /// Ignores set-user-ID and set-group-ID bits of files in the file system.
+    pub fn no_suid(&mut self, no_suid: bool) -> &mut Self {
+        self.inner.no_suid(no_suid);
+        self
+    }
+
+    /// Denies access to device special files in the file system.
+    ///
+    /// # Platform-specific behaviour
+    ///
+    /// Ignored on FreeBSD, where device files are only available through `devfs`.
+    pub fn no_dev(&mut self, no_dev: bool) -> &mut Self {
+        self.inner.no_dev(no_dev);
+        self
+    }
+
+    /// Denies execution of programs in the file system.
+    pub fn no_exec(&mut self, no_exec: bool) -> &mut Self {
+        self.inner.no_exec(no_exec);
+        self
+    }
+
+    /// Does not update access times of files in the file system.
+    pub fn no_atime(&mut self, no_atime: bool) -> &mut Self {
+        self.inner.no_atime(no_atime);
+        self
+    }
+
+    /// Updates access times only if they are older than the modification or change times.
+    ///
+    /// # Platform-specific behaviour
+    ///
+    /// Ignored on FreeBSD, macOS and IOS.
+    pub fn relatime(&mut self, relatime: bool) -> &mut Self {
+        self.inner.relatime(relatime);
+        self
+    }
+
+    /// Sets flags and file system specific data from a list of options.
+    ///
+    /// Replaces every flag and data previously set. Options only meaningful to userspace,
+    /// such as `noauto` or `x-*`, are ignored.
+    ///
+    /// # Platform-specific behaviour
+    ///
+    /// Only available on unix platforms.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use disket::mount::MountOptions;
+    /// use std::error::Error;
+    ///
+    /// # #[cfg(unix)]
+    /// fn main() -> Result<(), Box<dyn Error>> {
+    ///     MountOptions::new()
+    ///         .volume("/dev/sdb1")
+    ///         .mount_point("/mnt")
+    ///         .options(&"ro,nosuid,uid=1000,umask=022".into())
+    ///         .mount()?;
+    ///
+    ///     Ok(())
+    /// }
+    /// # #[cfg(not(unix))]
+    /// # fn main() {}
+    /// ```
+    #[cfg(unix)]
+    pub fn options(&mut self, options: &OptionString) -> &mut Self {
+        self.inner.flags(options.flags());
+        self.inner.data(options.data().map(Into::into));
+        self
+    }
+
     /// Mounts a volume with the options specified by `self`.
     ///
     /// See [`mount`] for details.
     pub fn mount(&self) -> Result<()> {
         sys::mount(&self.inner)
+            .map_err(|e| e.context(Operation::Mount, [self.inner.source(), self.inner.target()]))
+    }
+
+    /// Mounts a volume with the options specified by `self` and returns a guard that
+    /// unmounts it when dropped.
+    ///
+    /// See [`MountGuard`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use disket::mount::MountOptions;
+    /// use std::error::Error;
+    ///
+    /// fn main() -> Result<(), Box<dyn Error>> {
+    ///     let guard = MountOptions::new()
+    ///         .volume("/dev/sdb1")
+    ///         .mount_point("/mnt")
+    ///         .mount_scoped()?;
+    ///
+    ///     std::fs::write("/mnt/hello", "world")?;
+    ///     guard.unmount()?;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn mount_scoped(&self) -> Result<MountGuard> {
+        self.mount()?;
+        Ok(MountGuard::new(PathBuf::from(self.inner.target()), false))
+    }
+
+    /// Mounts a volume at a new directory in [`std::env::temp_dir`] and returns a guard
+    /// that unmounts it and removes the directory when dropped.
+    ///
+    /// The mount point set in `self` is ignored. The directory is removed right away if
+    /// mounting fails.
+    pub fn mount_temporary(&self) -> Result<MountGuard> {
+        let mount_point = guard::temporary_dir()?;
+        let mut options = self.clone();
+        options.mount_point(&mount_point);
+
+        if let Err(e) = options.mount() {
+            let _ = fs::remove_dir(&mount_point);
+            return Err(e);
+        }
+
+        Ok(MountGuard::new(mount_point, true))
     }
 }
 
@@ -110,8 +302,9 @@ impl MountOptions {
 ///
 /// Unmount with no platform-specific options:
 ///
-/// ```
+/// ```no_run
 /// use disket::mount::UnmountOptions;
+/// use std::error::Error;
 ///
 /// fn main() -> Result<(), Box<dyn Error>> {
 ///     UnmountOptions::new()
@@ -124,9 +317,12 @@ impl MountOptions {
 ///
 /// Unmount with platform-specific options:
 ///
-/// ```
+/// ```no_run
+/// # #[cfg(target_vendor = "apple")]
 /// use disket::{mount::UnmountOptions, os::mount::apple::{UnmountOptionsExt, MntFlags}};
+/// use std::error::Error;
 ///
+/// # #[cfg(target_vendor = "apple")]
 /// fn main() -> Result<(), Box<dyn Error>> {
 ///     UnmountOptions::new()
 ///         .mount_point("/mnt")
@@ -135,16 +331,26 @@ impl MountOptions {
 ///
 ///     Ok(())
 /// }
+/// # #[cfg(not(target_vendor = "apple"))]
+/// # fn main() {}
 /// ```
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct UnmountOptions {
     pub(crate) inner: sys::UnmountOptions,
+    recursive: bool,
+    retries: u32,
+    delay: Duration,
+    fallback: Fallback,
 }
 
 impl Default for UnmountOptions {
     fn default() -> Self {
         Self {
             inner: sys::UnmountOptions::new(),
+            recursive: false,
+            retries: 0,
+            delay: Duration::ZERO,
+            fallback: Fallback::None,
         }
     }
 }
@@ -161,11 +367,312 @@ impl UnmountOptions {
         self
     }
 
+    /// Sets whether every mount at or below the mount point is unmounted as well.
+    /// Defaults to `false`.
+    ///
+    /// See [`unmount_all_under`] for details.
+    pub fn recursive(&mut self, recursive: bool) -> &mut Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Sets whether the file system is unmounted even if it is busy. Defaults to `false`.
+    ///
+    /// On Linux, Android, FreeBSD, macOS and IOS this corresponds to `MNT_FORCE`. Not
+    /// supported on Windows, where unmounting fails with an [`Error::Unsupported`] error.
+    pub fn force(&mut self, force: bool) -> &mut Self {
+        self.inner.force(force);
+        self
+    }
+
+    /// Sets whether the file system is detached right away and cleaned up once it is no
+    /// longer busy. Defaults to `false`.
+    ///
+    /// On Linux and Android this corresponds to `MNT_DETACH`. Not supported on other
+    /// platforms, where unmounting fails with an [`Error::Unsupported`] error.
+    pub fn lazy(&mut self, lazy: bool) -> &mut Self {
+        self.inner.lazy(lazy);
+        self
+    }
+
+    /// Retries unmounting up to `attempts` times while the file system is busy, waiting
+    /// `delay` before the first retry and twice as long before each of the next ones.
+    /// Defaults to no retries.
+    pub fn retry(&mut self, attempts: u32, delay: Duration) -> &mut Self {
+        self.retries = attempts;
+        self.delay = delay;
+        self
+    }
+
+    /// Sets what to do when unmounting still fails after every retry. Defaults to
+    /// [`Fallback::None`].
+    pub fn fallback(&mut self, fallback: Fallback) -> &mut Self {
+        self.fallback = fallback;
+        self
+    }
+
     /// Unmounts a volume with the options specified by `self`
     ///
     /// See [`unmount`] for details.
+    ///
+    /// # Errors
+    ///
+    /// The fallback is only tried if the file system is still busy, and then only its
+    /// error is returned. Any other error is returned right away.
+    ///
+    /// # Examples
+    ///
+    /// Wait up to about three seconds for the file system to be released, then detach it:
+    ///
+    /// ```no_run
+    /// use disket::mount::{Fallback, UnmountOptions};
+    /// use std::error::Error;
+    /// use std::time::Duration;
+    ///
+    /// fn main() -> Result<(), Box<dyn Error>> {
+    ///     UnmountOptions::new()
+    ///         .mount_point("/mnt")
+    ///         .retry(4, Duration::from_millis(200))
+    ///         .fallback(Fallback::Lazy)
+    ///         .unmount()?;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
     pub fn unmount(&self) -> Result<()> {
-        sys::unmount(&self.inner)
+        let mut retries = self.retries;
+        let mut delay = self.delay;
+
+        let result = loop {
+            match self.unmount_with(&self.inner) {
+                Err(Error::Busy { .. }) if retries > 0 => {
+                    thread::sleep(delay);
+                    retries -= 1;
+                    delay = delay.saturating_mul(2);
+                }
+                result => break result,
+            }
+        };
+
+        let mut inner = self.inner.clone();
+
+        match (result, self.fallback) {
+            (Err(Error::Busy { .. }), Fallback::Lazy) => self.unmount_with(inner.lazy(true)),
+            (Err(Error::Busy { .. }), Fallback::Force) => self.unmount_with(inner.force(true)),
+            (result, _) => result,
+        }
+    }
+
+    fn unmount_with(&self, inner: &sys::UnmountOptions) -> Result<()> {
+        if self.recursive {
+            sys::unmount_all(inner)
+        } else {
+            sys::unmount(inner)
+        }
+        .map_err(|e| e.context(Operation::Unmount, [inner.target()]))
+    }
+}
+
+/// Options used to configure a bind mount.
+///
+/// A bind mount makes a directory, or a single file, available at another place in the
+/// file system tree. Start by calling `new`, chain calls to set every option and then call
+/// `bind`.
+///
+/// # Platform-specific behaviour
+///
+/// On Linux and Android 5.12 and later, a read-only bind mount is made read-only before
+/// it is attached. Older kernels ignore the read-only flag when creating a bind mount, so
+/// the new mounts are remounted as read-only. Flags that can't be cleared, such as
+/// `nosuid` in a user namespace, are kept.
+///
+/// On FreeBSD, a `nullfs` file system is mounted instead. Recursive bind mounts are not
+/// supported, since `nullfs` does not cross into the mounts under its source.
+///
+/// Not supported on macOS, IOS and Windows.
+///
+/// # Errors
+///
+/// Returns an [`Error::Unsupported`] error where bind mounts, or recursive bind mounts,
+/// are not supported.
+///
+/// # Examples
+///
+/// Make `/usr` available as read-only inside a sandbox:
+///
+/// ```no_run
+/// use disket::mount::BindOptions;
+/// use std::error::Error;
+///
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     BindOptions::new()
+///         .source("/usr")
+///         .target("/srv/sandbox/usr")
+///         .recursive(true)
+///         .read_only(true)
+///         .bind()?;
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BindOptions {
+    pub(crate) source: OsString,
+    pub(crate) target: OsString,
+    pub(crate) recursive: bool,
+    pub(crate) read_only: bool,
+}
+
+impl BindOptions {
+    /// Creates a new set of options with default values.
+    pub fn new() -> Self {
+        BindOptions::default()
+    }
+
+    /// Sets the path to be bound.
+    pub fn source<T: AsRef<OsStr>>(&mut self, source: T) -> &mut Self {
+        self.source = source.as_ref().to_os_string();
+        self
+    }
+
+    /// Sets the path where `source` is made available.
+    pub fn target<T: AsRef<OsStr>>(&mut self, target: T) -> &mut Self {
+        self.target = target.as_ref().to_os_string();
+        self
+    }
+
+    /// Binds every mount under `source` as well.
+    pub fn recursive(&mut self, recursive: bool) -> &mut Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Makes the bind mount read-only. The mount at `source` is not affected.
+    pub fn read_only(&mut self, read_only: bool) -> &mut Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Creates a bind mount with the options specified by `self`.
+    pub fn bind(&self) -> Result<()> {
+        sys::bind(self).map_err(|e| e.context("bind", [&self.source, &self.target]))
+    }
+}
+
+/// Options used to change the flags of a mounted file system.
+///
+/// Start by calling [`remount`] or `new`, chain calls to set the flags to be changed and
+/// then call `remount`. Flags that are not set are kept as they are, so a mount stays
+/// `nosuid` unless `no_suid(false)` is called.
+///
+/// # Platform-specific behaviour
+///
+/// On Linux and Android, the current flags of the mount, and of its superblock such as
+/// `sync` or `lazytime`, are read from `/proc/self/mountinfo` and the file system is
+/// remounted with `MS_REMOUNT`. File system specific data is left as is.
+///
+/// On FreeBSD, the current flags are read with `statfs` and the file system is updated
+/// with `MNT_UPDATE`. `no_dev` and `relatime` are ignored.
+///
+/// Not supported on macOS, IOS and Windows.
+///
+/// # Errors
+///
+/// On Linux and Android, an [`Error::NotMounted`] error is returned if `mount_point` is
+/// not a mount point. An [`Error::Unsupported`] error is returned where remounting is not
+/// supported.
+///
+/// # Examples
+///
+/// Make a mount writable for a moment:
+///
+/// ```no_run
+/// use disket::mount;
+/// use std::error::Error;
+///
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     mount::remount("/boot").read_only(false).remount()?;
+///     std::fs::write("/boot/loader.conf", "timeout 3")?;
+///     mount::remount("/boot").read_only(true).remount()?;
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RemountOptions {
+    pub(crate) mount_point: OsString,
+    pub(crate) read_only: Option<bool>,
+    pub(crate) no_suid: Option<bool>,
+    pub(crate) no_dev: Option<bool>,
+    pub(crate) no_exec: Option<bool>,
+    pub(crate) no_atime: Option<bool>,
+    pub(crate) relatime: Option<bool>,
+    pub(crate) mount_point_only: bool,
+}
+
+impl RemountOptions {
+    /// Creates a new set of options that changes nothing.
+    pub fn new() -> Self {
+        RemountOptions::default()
+    }
+
+    /// Sets the mount point of the file system to be remounted.
+    pub fn mount_point<T: AsRef<OsStr>>(&mut self, mount_point: T) -> &mut Self {
+        self.mount_point = mount_point.as_ref().to_os_string();
+        self
+    }
+
+    /// Makes the file system read-only or writable.
+    pub fn read_only(&mut self, read_only: bool) -> &mut Self {
+        self.read_only = Some(read_only);
+        self
+    }
+
+    /// Changes whether set-user-ID and set-group-ID bits are ignored.
+    pub fn no_suid(&mut self, no_suid: bool) -> &mut Self {
+        self.no_suid = Some(no_suid);
+        self
+    }
+
+    /// Changes whether access to device special files is denied.
+    pub fn no_dev(&mut self, no_dev: bool) -> &mut Self {
+        self.no_dev = Some(no_dev);
+        self
+    }
+
+    /// Changes whether execution of programs is denied.
+    pub fn no_exec(&mut self, no_exec: bool) -> &mut Self {
+        self.no_exec = Some(no_exec);
+        self
+    }
+
+    /// Changes whether access times are updated.
+    pub fn no_atime(&mut self, no_atime: bool) -> &mut Self {
+        self.no_atime = Some(no_atime);
+        self
+    }
+
+    /// Changes whether access times are only updated if they are older than the
+    /// modification or change times.
+    pub fn relatime(&mut self, relatime: bool) -> &mut Self {
+        self.relatime = Some(relatime);
+        self
+    }
+
+    /// Only changes this mount point, leaving the file system and other mounts of it, such
+    /// as bind mounts, as they are.
+    ///
+    /// # Platform-specific behaviour
+    ///
+    /// On Linux and Android, this adds `MS_BIND` to the remount. Ignored on other platforms.
+    pub fn mount_point_only(&mut self, mount_point_only: bool) -> &mut Self {
+        self.mount_point_only = mount_point_only;
+        self
+    }
+
+    /// Remounts the file system with the options specified by `self`.
+    pub fn remount(&self) -> Result<()> {
+        sys::remount(self).map_err(|e| e.context("remount", [&self.mount_point]))
     }
 }
 
@@ -181,7 +688,10 @@ impl UnmountOptions {
 ///
 /// On Windows, this function corresponds to `SetVolumeMountPointW`.
 ///
-/// On Linux and Android, this function corresponds to the `mount` syscall.
+/// On Linux and Android, this function corresponds to the `fsopen`, `fsconfig`, `fsmount`
+/// and `move_mount` syscalls, falling back to the `mount` syscall on kernels older than 5.2
+/// or when the flags can't be expressed with them. Use
+/// `disket::os::mount::linux::MountOptionsExt::api` to choose between them.
 ///
 /// On FreeBSD , this function corresponds to the `nmount` syscall.
 ///
@@ -194,8 +704,9 @@ impl UnmountOptions {
 ///
 /// # Examples
 ///
-/// ```
+/// ```no_run
 /// use disket::mount;
+/// use std::error::Error;
 ///
 /// fn main() -> Result<(), Box<dyn Error>> {
 ///     mount::mount("/dev/sdc1", "/mnt")?; // Mount /dev/sdc1 at /mnt
@@ -246,8 +757,9 @@ pub fn mount<T: AsRef<OsStr>>(volume: T, mount_point: T) -> Result<()> {
 ///
 /// # Examples
 ///
-/// ```
+/// ```no_run
 /// use disket::mount;
+/// use std::error::Error;
 ///
 /// fn main() -> Result<(), Box<dyn Error>> {
 ///     mount::unmount("/mnt")?; // Unmount volume mounted at /mnt
@@ -269,3 +781,160 @@ pub fn mount<T: AsRef<OsStr>>(volume: T, mount_point: T) -> Result<()> {
 pub fn unmount<T: AsRef<OsStr>>(mount_point: T) -> Result<()> {
     UnmountOptions::new().mount_point(mount_point).unmount()
 }
+
+/// Makes `source` available at `target` as well.
+///
+/// This function is interchangeable with manually calling `bind` on [`BindOptions`]
+/// with `source` and `target` set. See [`BindOptions`] for details.
+///
+/// # Examples
+///
+/// ```no_run
+/// use disket::mount;
+/// use std::error::Error;
+///
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     mount::bind("/home/user/project", "/srv/sandbox/project")?;
+///     Ok(())
+/// }
+/// ```
+pub fn bind<T: AsRef<OsStr>>(source: T, target: T) -> Result<()> {
+    BindOptions::new().source(source).target(target).bind()
+}
+
+/// Makes `source`, and every mount under it, available at `target` as well.
+///
+/// This function is interchangeable with manually calling `bind` on [`BindOptions`]
+/// with `source`, `target` and `recursive` set. See [`BindOptions`] for details.
+pub fn bind_recursive<T: AsRef<OsStr>>(source: T, target: T) -> Result<()> {
+    BindOptions::new()
+        .source(source)
+        .target(target)
+        .recursive(true)
+        .bind()
+}
+
+/// Returns a [`RemountOptions`] for the file system mounted at `mount_point`.
+///
+/// Chain calls to set the flags to be changed and then call `remount`. See
+/// [`RemountOptions`] for details.
+pub fn remount<T: AsRef<OsStr>>(mount_point: T) -> RemountOptions {
+    let mut options = RemountOptions::new();
+    options.mount_point(mount_point);
+    options
+}
+
+/// Unmounts every mount at or below `path`, children before their parents.
+///
+/// `path` does not need to be a mount point itself. Mounts hiding others at the same place
+/// are unmounted first. A failure does not stop the remaining mounts from being unmounted,
+/// although a parent of a mount that could not be unmounted will usually fail as busy.
+///
+/// This function is interchangeable with manually calling `unmount` on [`UnmountOptions`]
+/// with `mount_point` and `recursive` set.
+///
+/// # Platform-specific behaviour
+///
+/// On Linux and Android, the mounts are found in `/proc/self/mountinfo`.
+///
+/// Not supported on FreeBSD, macOS, IOS and Windows.
+///
+/// # Errors
+///
+/// Returns an [`Error::Unmount`] error listing the mount point and error of each mount that
+/// could not be unmounted, once every other mount is unmounted. If they were all busy, an
+/// [`Error::Busy`] error with their mount points is returned instead, so retries and
+/// fallbacks of [`UnmountOptions`] apply to them. Returns an [`Error::Unsupported`] error
+/// where recursive unmounts are not supported.
+///
+/// # Examples
+///
+/// Tear down a container root file system:
+///
+/// ```no_run
+/// use disket::mount;
+/// use std::error::Error;
+///
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     mount::unmount_all_under("/var/lib/containers/app/rootfs")?;
+///     Ok(())
+/// }
+/// ```
+pub fn unmount_all_under<T: AsRef<OsStr>>(path: T) -> Result<()> {
+    UnmountOptions::new()
+        .mount_point(path)
+        .recursive(true)
+        .unmount()
+}
+
+/// Moves the mount at `from`, along with every mount under it, to `to`.
+///
+/// # Platform-specific behaviour
+///
+/// On Linux and Android, this function corresponds to the `move_mount` syscall, falling
+/// back to the `mount` syscall with `MS_MOVE` on kernels older than 5.2.
+///
+/// Not supported on FreeBSD, macOS, IOS and Windows.
+///
+/// # Errors
+///
+/// Returns an [`Error::Unsupported`] error where moving mounts is not supported.
+///
+/// # Examples
+///
+/// ```no_run
+/// use disket::mount;
+/// use std::error::Error;
+///
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     mount::move_mount("/run/staging", "/srv/app")?;
+///     Ok(())
+/// }
+/// ```
+pub fn move_mount<T: AsRef<OsStr>>(from: T, to: T) -> Result<()> {
+    let (from, to) = (from.as_ref(), to.as_ref());
+    sys::move_mount(from, to).map_err(|e| e.context("move", [from, to]))
+}
+
+/// Moves the mount at `source` to `target`, taking the place of the file system mounted
+/// there without any moment where `target` is empty.
+///
+/// The old file system is detached, so it goes away once processes stop using it.
+///
+/// # Platform-specific behaviour
+///
+/// On Linux and Android, the mount is first moved beneath the one at `target` with
+/// `MOVE_MOUNT_BENEATH`, and the old one is then unmounted with `MNT_DETACH`. This needs
+/// Linux 6.5 or later. To stack the new mount on top of the old one instead, use
+/// [`move_mount`].
+///
+/// Not supported on FreeBSD, macOS, IOS and Windows.
+///
+/// # Errors
+///
+/// Returns an [`Error::Unsupported`] error where replacing mounts is not supported,
+/// including when the kernel can't place the mount beneath the one at `target`.
+///
+/// # Examples
+///
+/// Swap the application volume mounted at `/srv/app` for a new one:
+///
+/// ```no_run
+/// use disket::mount::{self, MountOptions};
+/// use std::error::Error;
+///
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     MountOptions::new()
+///         .volume("LABEL=app-2.0")
+///         .mount_point("/run/staging")
+///         .read_only(true)
+///         .mount()?;
+///     mount::replace("/run/staging", "/srv/app")?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn replace<T: AsRef<OsStr>>(source: T, target: T) -> Result<()> {
+    let (source, target) = (source.as_ref(), target.as_ref());
+    sys::replace(source, target).map_err(|e| e.context("replace", [source, target]))
+}
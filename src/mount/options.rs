@@ -0,0 +1,314 @@
+use super::sys;
+use std::fmt;
+
+/// Options that are only meaningful to userspace tools and never reach the kernel.
+const USER_OPTIONS: &[&str] = &[
+    "auto", "noauto", "user", "nouser", "users", "owner", "group", "nofail", "_netdev", "loop",
+    "failok", "late",
+];
+
+/// Prefixes of options that are only meaningful to userspace tools.
+const USER_PREFIXES: &[&str] = &["x-", "comment=", "loop=", "offset=", "sizelimit="];
+
+/// Options that imply flags, as if the flags were given right after them.
+const IMPLIED_OPTIONS: &[(&str, &[&str])] = &[
+    ("user", &["noexec", "nosuid", "nodev"]),
+    ("users", &["noexec", "nosuid", "nodev"]),
+    ("owner", &["nosuid", "nodev"]),
+    ("group", &["nosuid", "nodev"]),
+];
+
+/// A list of mount options in the format used by `mount(8)` and `fstab(5)`.
+///
+/// An option string, such as `ro,nosuid,uid=1000,umask=022`, mixes three kinds of options:
+///
+/// - Options that map to a kernel flag, such as `ro` or `nosuid`, and their negations, such
+///   as `rw` or `suid`.
+/// - Options only meaningful to userspace, such as `noauto`, `nofail` or `x-*`.
+/// - File system specific options, such as `uid=1000`, that are passed as data.
+///
+/// Options are kept in the order they were given, so parsing and formatting a string gives
+/// back the same string. Later options override earlier ones, just like in `mount(8)`.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(unix)]
+/// # fn main() {
+/// use disket::mount::OptionString;
+///
+/// let options = OptionString::from("ro,nosuid,uid=1000,umask=022,x-systemd.automount");
+///
+/// assert_eq!(options.data().as_deref(), Some("uid=1000,umask=022"));
+/// assert_eq!(options.user_options().collect::<Vec<_>>(), ["x-systemd.automount"]);
+/// assert_eq!(options.to_string(), "ro,nosuid,uid=1000,umask=022,x-systemd.automount");
+/// # }
+/// # #[cfg(not(unix))]
+/// # fn main() {}
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OptionString {
+    options: Vec<String>,
+}
+
+impl OptionString {
+    /// Creates an empty list of options.
+    pub fn new() -> Self {
+        OptionString::default()
+    }
+
+    /// Creates a list of options from platform flags and file system specific data.
+    ///
+    /// This is the inverse of [`OptionString::flags`] and [`OptionString::data`].
+    pub fn from_flags(flags: sys::Flags, data: Option<&str>) -> Self {
+        let mut options = OptionString::new();
+        let mut remaining = flags;
+
+        for (name, flag, clear) in sys::FLAG_OPTIONS {
+            if !clear && !flag.is_empty() && remaining.contains(*flag) {
+                options.push(*name);
+                remaining.remove(*flag);
+            }
+        }
+
+        if let Some(data) = data {
+            options.options.extend(split(data));
+        }
+
+        options
+    }
+
+    /// Appends an option to the end of the list.
+    pub fn push<T: Into<String>>(&mut self, option: T) -> &mut Self {
+        self.options.push(option.into());
+        self
+    }
+
+    /// Removes every option called `name`, regardless of its value.
+    ///
+    /// Returns `true` if any option was removed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.options.len();
+        self.options.retain(|option| self::name(option) != name);
+        len != self.options.len()
+    }
+
+    /// Returns `true` if there is an option called `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.iter().any(|option| self::name(option) == name)
+    }
+
+    /// Returns the value of the last option called `name`, if it has one.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.iter()
+            .rev()
+            .filter_map(|option| option.split_once('='))
+            .find(|(n, _)| *n == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Returns an iterator over every option in the order they were given.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.options.iter().map(String::as_str)
+    }
+
+    /// Returns `true` if there are no options.
+    pub fn is_empty(&self) -> bool {
+        self.options.is_empty()
+    }
+
+    /// Returns the platform flags set by the options.
+    ///
+    /// `defaults` and negations, such as `rw` or `exec`, clear the flags set by earlier
+    /// options. As in `mount(8)`, `user` and `users` imply `noexec`, `nosuid` and `nodev`,
+    /// and `owner` and `group` imply `nosuid` and `nodev`, unless later options say
+    /// otherwise.
+    pub fn flags(&self) -> sys::Flags {
+        let mut flags = sys::Flags::empty();
+
+        for option in self.iter() {
+            if option == "defaults" {
+                sys::DEFAULT_OPTIONS
+                    .iter()
+                    .for_each(|option| apply(&mut flags, option));
+            } else if let Some((_, implied)) = IMPLIED_OPTIONS.iter().find(|(o, _)| *o == option) {
+                implied.iter().for_each(|option| apply(&mut flags, option));
+            } else {
+                apply(&mut flags, option);
+            }
+        }
+
+        flags
+    }
+
+    /// Returns the file system specific options as a comma-separated list.
+    ///
+    /// These are the options that are neither flags nor userspace options. Returns `None`
+    /// if there is none.
+    pub fn data(&self) -> Option<String> {
+        let data: Vec<&str> = self
+            .iter()
+            .filter(|option| kind(option) == Kind::Data)
+            .collect();
+
+        (!data.is_empty()).then(|| data.join(","))
+    }
+
+    /// Returns an iterator over the options only meaningful to userspace.
+    ///
+    /// These include options such as `noauto`, `nofail`, `_netdev`, `x-*` and `comment=*`.
+    pub fn user_options(&self) -> impl Iterator<Item = &str> {
+        self.iter().filter(|option| kind(option) == Kind::User)
+    }
+}
+
+impl From<&str> for OptionString {
+    fn from(s: &str) -> Self {
+        OptionString {
+            options: split(s).collect(),
+        }
+    }
+}
+
+impl fmt::Display for OptionString {
+    /// Formats the options as a comma-separated list. An empty list is formatted as `defaults`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.options.is_empty() {
+            return f.write_str("defaults");
+        }
+
+        f.write_str(&self.options.join(","))
+    }
+}
+
+#[derive(PartialEq, Eq)]
+enum Kind {
+    Flag,
+    User,
+    Data,
+}
+
+fn kind(option: &str) -> Kind {
+    if option == "defaults" || sys::FLAG_OPTIONS.iter().any(|(name, ..)| *name == option) {
+        Kind::Flag
+    } else if USER_OPTIONS.contains(&option) || USER_PREFIXES.iter().any(|p| option.starts_with(p))
+    {
+        Kind::User
+    } else {
+        Kind::Data
+    }
+}
+
+fn apply(flags: &mut sys::Flags, option: &str) {
+    if let Some((_, flag, clear)) = sys::FLAG_OPTIONS.iter().find(|(name, ..)| *name == option) {
+        flags.set(*flag, !clear);
+    }
+}
+
+fn name(option: &str) -> &str {
+    option.split_once('=').map_or(option, |(name, _)| name)
+}
+
+/// Splits a comma-separated list of options. Commas between double quotes, as in
+/// `context="a,b"`, do not split options.
+pub(crate) fn split(s: &str) -> impl Iterator<Item = String> + '_ {
+    let mut quoted = false;
+
+    s.split(move |c| {
+        if c == '"' {
+            quoted = !quoted;
+        }
+
+        c == ',' && !quoted
+    })
+    .map(str::trim)
+    .filter(|option| !option.is_empty())
+    .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for options in [
+            "ro,nosuid,uid=1000,umask=022,x-systemd.automount",
+            "defaults",
+            "rw,context=\"system_u:object_r:tmp_t:s0:c127,c456\",noexec",
+        ] {
+            assert_eq!(OptionString::from(options).to_string(), options);
+        }
+    }
+
+    #[test]
+    fn split_options() {
+        let options = OptionString::from(" ro, ,uid=1000,,context=\"a,b\" ");
+
+        assert_eq!(
+            options.iter().collect::<Vec<_>>(),
+            ["ro", "uid=1000", "context=\"a,b\""]
+        );
+        assert_eq!(OptionString::from("").to_string(), "defaults");
+    }
+
+    #[test]
+    fn kinds() {
+        let options = OptionString::from("ro,noauto,uid=1000,x-gvfs-show,comment=x,umask=022");
+
+        assert_eq!(options.data().as_deref(), Some("uid=1000,umask=022"));
+        assert_eq!(
+            options.user_options().collect::<Vec<_>>(),
+            ["noauto", "x-gvfs-show", "comment=x"]
+        );
+        assert_eq!(OptionString::from("ro,noauto").data(), None);
+    }
+
+    #[test]
+    fn values() {
+        let mut options = OptionString::from("uid=1000,ro,uid=0");
+
+        assert_eq!(options.value("uid"), Some("0"));
+        assert_eq!(options.value("ro"), None);
+        assert!(options.contains("uid"));
+        assert!(options.remove("uid"));
+        assert!(!options.remove("uid"));
+        assert_eq!(options.to_string(), "ro");
+    }
+
+    #[test]
+    fn later_options_override() {
+        let flags = |s| OptionString::from(s).flags();
+
+        assert_eq!(flags("ro,rw"), flags("rw"));
+        assert_eq!(flags("nosuid,defaults"), flags("defaults"));
+        assert_ne!(flags("ro"), flags("rw"));
+    }
+
+    #[test]
+    fn implied_options() {
+        let flags = |s| OptionString::from(s).flags();
+
+        assert_eq!(flags("user"), flags("noexec,nosuid,nodev"));
+        assert_eq!(flags("users,exec"), flags("nosuid,nodev"));
+        assert_eq!(flags("owner"), flags("nosuid,nodev"));
+        assert_eq!(flags("user,exec,dev,suid"), flags(""));
+    }
+
+    #[test]
+    fn from_flags() {
+        let options = OptionString::from("ro,nosuid,uid=1000");
+        let flags = options.flags();
+
+        assert_eq!(
+            OptionString::from_flags(flags, options.data().as_deref()).flags(),
+            flags
+        );
+        assert_eq!(
+            OptionString::from_flags(flags, Some("uid=1000"))
+                .data()
+                .as_deref(),
+            Some("uid=1000")
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{Error, Result};
+use nix::mount::{self, MsFlags};
+use std::ffi::OsStr;
+
+/// How mount and unmount events propagate between mounts.
+///
+/// Check [mount_namespaces(7)] for details.
+///
+/// [mount_namespaces(7)]: https://man7.org/linux/man-pages/man7/mount_namespaces.7.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Propagation {
+    /// Events propagate to and from every mount in the same peer group.
+    Shared,
+    /// Events propagate from the master peer group, but not back to it.
+    Slave,
+    /// Events neither propagate to nor from other mounts.
+    Private,
+    /// Like [`Propagation::Private`], and the mount can't be bind mounted either.
+    Unbindable,
+}
+
+impl Propagation {
+    fn flag(self) -> MsFlags {
+        match self {
+            Propagation::Shared => MsFlags::MS_SHARED,
+            Propagation::Slave => MsFlags::MS_SLAVE,
+            Propagation::Private => MsFlags::MS_PRIVATE,
+            Propagation::Unbindable => MsFlags::MS_UNBINDABLE,
+        }
+    }
+}
+
+/// Changes the propagation type of the mount at `path`.
+///
+/// If `recursive` is `true`, every mount under `path` is changed as well.
+///
+/// # Platform-specific behaviour
+///
+/// Only available on Linux and Android.
+///
+/// # Examples
+///
+/// Make every mount private, as usually done when setting up a new mount namespace:
+///
+/// ```no_run
+/// use disket::mount::{self, Propagation};
+/// use std::error::Error;
+///
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     mount::set_propagation("/", Propagation::Private, true)?;
+///     Ok(())
+/// }
+/// ```
+pub fn set_propagation<T: AsRef<OsStr>>(
+    path: T,
+    propagation: Propagation,
+    recursive: bool,
+) -> Result<()> {
+    let mut flags = propagation.flag();
+    flags.set(MsFlags::MS_REC, recursive);
+
+    mount::mount(
+        None::<&OsStr>,
+        path.as_ref(),
+        None::<&OsStr>,
+        flags,
+        None::<&OsStr>,
+    )
+    .map_err(|e| Error::from(e).context("set propagation of", [path.as_ref()]))?;
+
+    Ok(())
+}
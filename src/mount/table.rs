@@ -0,0 +1,267 @@
+use super::Propagation;
+use crate::common::unix::unescape;
+use crate::Result;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+const MOUNTINFO: &str = "/proc/self/mountinfo";
+
+/// A single entry of the mount table.
+///
+/// Every field corresponds to a column of `/proc/self/mountinfo`. Check [proc_pid_mountinfo(5)]
+/// for details about each of them.
+///
+/// [proc_pid_mountinfo(5)]: https://man7.org/linux/man-pages/man5/proc_pid_mountinfo.5.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MountEntry {
+    id: u32,
+    parent_id: u32,
+    major: u32,
+    minor: u32,
+    root: PathBuf,
+    mount_point: PathBuf,
+    options: OsString,
+    optional_fields: Vec<OsString>,
+    fs_type: OsString,
+    source: OsString,
+    super_options: OsString,
+}
+
+impl MountEntry {
+    /// Unique identifier of the mount.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Identifier of the parent mount, or of itself for the root of the mount tree.
+    pub fn parent_id(&self) -> u32 {
+        self.parent_id
+    }
+
+    /// Major number of the device backing the file system.
+    pub fn major(&self) -> u32 {
+        self.major
+    }
+
+    /// Minor number of the device backing the file system.
+    pub fn minor(&self) -> u32 {
+        self.minor
+    }
+
+    /// Path of the directory in the file system that forms the root of this mount.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Path of the mount point relative to the process root directory.
+    pub fn mount_point(&self) -> &Path {
+        &self.mount_point
+    }
+
+    /// Per-mount options as a comma-separated list.
+    pub fn options(&self) -> &OsStr {
+        &self.options
+    }
+
+    /// Optional fields in the form `tag[:value]`, such as `shared:1` or `master:2`.
+    pub fn optional_fields(&self) -> &[OsString] {
+        &self.optional_fields
+    }
+
+    /// Propagation type of the mount.
+    ///
+    /// A mount that is both shared and a slave is reported as [`Propagation::Shared`].
+    /// Use [`MountEntry::master`] to tell them apart.
+    pub fn propagation(&self) -> Propagation {
+        if self.peer_group().is_some() {
+            Propagation::Shared
+        } else if self.master().is_some() {
+            Propagation::Slave
+        } else if self.optional_fields.iter().any(|f| f == "unbindable") {
+            Propagation::Unbindable
+        } else {
+            Propagation::Private
+        }
+    }
+
+    /// Peer group of the mount, if it is shared.
+    pub fn peer_group(&self) -> Option<u32> {
+        self.optional_field("shared")
+    }
+
+    /// Peer group the mount receives events from, if it is a slave.
+    pub fn master(&self) -> Option<u32> {
+        self.optional_field("master")
+    }
+
+    /// File system type in the form `type[.subtype]`.
+    pub fn fs_type(&self) -> &OsStr {
+        &self.fs_type
+    }
+
+    /// File system specific information, usually the device. May be `none`.
+    pub fn source(&self) -> &OsStr {
+        &self.source
+    }
+
+    /// Per-superblock options as a comma-separated list.
+    pub fn super_options(&self) -> &OsStr {
+        &self.super_options
+    }
+
+    fn optional_field(&self, tag: &str) -> Option<u32> {
+        self.optional_fields.iter().find_map(|field| {
+            let (name, value) = field.to_str()?.split_once(':')?;
+            (name == tag).then(|| value.parse().ok())?
+        })
+    }
+
+    fn parse(line: &[u8]) -> Option<Self> {
+        let mut fields = line.split(|&b| b == b' ');
+
+        let id = number(fields.next()?)?;
+        let parent_id = number(fields.next()?)?;
+        let (major, minor) = std::str::from_utf8(fields.next()?).ok()?.split_once(':')?;
+        let root = unescape(fields.next()?).into();
+        let mount_point = unescape(fields.next()?).into();
+        let options = unescape(fields.next()?);
+
+        let mut optional_fields = Vec::new();
+        for field in fields.by_ref() {
+            if field == b"-" {
+                break;
+            }
+
+            optional_fields.push(unescape(field));
+        }
+
+        Some(Self {
+            id,
+            parent_id,
+            major: major.parse().ok()?,
+            minor: minor.parse().ok()?,
+            root,
+            mount_point,
+            options,
+            optional_fields,
+            fs_type: unescape(fields.next()?),
+            source: unescape(fields.next()?),
+            super_options: unescape(fields.next()?),
+        })
+    }
+}
+
+fn number(field: &[u8]) -> Option<u32> {
+    std::str::from_utf8(field).ok()?.parse().ok()
+}
+
+/// Returns every entry of the mount table.
+///
+/// Entries are returned in the same order the kernel reports them.
+///
+/// # Platform-specific behaviour
+///
+/// This function is only available on Linux and Android, where it reads `/proc/self/mountinfo`.
+///
+/// # Errors
+///
+/// Returns an error if the mount table can not be read or one of its lines is malformed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use disket::mount;
+/// use std::error::Error;
+///
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     let mounted = mount::mounts()?
+///         .iter()
+///         .any(|entry| entry.mount_point() == "/mnt");
+///
+///     if !mounted {
+///         mount::mount("/dev/sdc1", "/mnt")?;
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub fn mounts() -> Result<Vec<MountEntry>> {
+    let content = fs::read(MOUNTINFO)?;
+
+    content
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            MountEntry::parse(line).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "malformed mountinfo line: {}",
+                        String::from_utf8_lossy(line)
+                    ),
+                )
+                .into()
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Option<MountEntry> {
+        MountEntry::parse(line.as_bytes())
+    }
+
+    #[test]
+    fn escaped_paths() {
+        let entry = parse(
+            r"36 35 98:0 /my\040dir /mnt/a\011b\134c rw,noatime - ext3 /dev/root rw,errors=continue",
+        )
+        .unwrap();
+
+        assert_eq!((entry.id(), entry.parent_id()), (36, 35));
+        assert_eq!((entry.major(), entry.minor()), (98, 0));
+        assert_eq!(entry.root(), Path::new("/my dir"));
+        assert_eq!(entry.mount_point(), Path::new("/mnt/a\tb\\c"));
+        assert_eq!(entry.options(), "rw,noatime");
+        assert_eq!(entry.fs_type(), "ext3");
+        assert_eq!(entry.source(), "/dev/root");
+        assert_eq!(entry.super_options(), "rw,errors=continue");
+    }
+
+    #[test]
+    fn optional_fields() {
+        let entry = parse("21 1 0:19 / /sys rw - sysfs sysfs rw").unwrap();
+        assert!(entry.optional_fields().is_empty());
+        assert_eq!(entry.propagation(), Propagation::Private);
+
+        let entry = parse("22 21 0:20 / /run rw shared:5 master:1 - tmpfs tmpfs rw").unwrap();
+        assert_eq!(entry.optional_fields(), ["shared:5", "master:1"]);
+        assert_eq!((entry.peer_group(), entry.master()), (Some(5), Some(1)));
+        assert_eq!(entry.propagation(), Propagation::Shared);
+
+        let entry = parse("23 21 0:21 / /srv rw master:3 - tmpfs tmpfs rw").unwrap();
+        assert_eq!(entry.propagation(), Propagation::Slave);
+
+        let entry = parse("24 21 0:22 / /opt rw unbindable - tmpfs tmpfs rw").unwrap();
+        assert_eq!(entry.propagation(), Propagation::Unbindable);
+    }
+
+    #[test]
+    fn malformed_lines() {
+        for line in [
+            "",
+            "21 1 0:19 / /sys rw sysfs sysfs rw",
+            "21 1 0:19 / /sys rw shared:1 - sysfs sysfs",
+            "21 1 019 / /sys rw - sysfs sysfs rw",
+            "x 1 0:19 / /sys rw - sysfs sysfs rw",
+            "21 1 0:x / /sys rw - sysfs sysfs rw",
+            "21 1 0:19 /",
+        ] {
+            assert_eq!(parse(line), None, "{line:?}");
+        }
+    }
+}
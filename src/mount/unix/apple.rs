@@ -1,6 +1,39 @@
+use crate::mount::{BindOptions, RemountOptions};
 use crate::Result;
 use nix::mount::{self, MntFlags};
-use std::ffi::OsString;
+use std::ffi::{OsStr, OsString};
+use std::io;
+
+pub type Flags = MntFlags;
+
+/// Options of `mount(8)` that map to a flag. The last field is `true` when
+/// the option clears the flag instead of setting it.
+pub const FLAG_OPTIONS: &[(&str, MntFlags, bool)] = &[
+    ("ro", MntFlags::MNT_RDONLY, false),
+    ("rw", MntFlags::MNT_RDONLY, true),
+    ("nosuid", MntFlags::MNT_NOSUID, false),
+    ("suid", MntFlags::MNT_NOSUID, true),
+    ("nodev", MntFlags::MNT_NODEV, false),
+    ("dev", MntFlags::MNT_NODEV, true),
+    ("noexec", MntFlags::MNT_NOEXEC, false),
+    ("exec", MntFlags::MNT_NOEXEC, true),
+    ("sync", MntFlags::MNT_SYNCHRONOUS, false),
+    ("async", MntFlags::MNT_ASYNC, false),
+    ("noasync", MntFlags::MNT_ASYNC, true),
+    ("noatime", MntFlags::MNT_NOATIME, false),
+    ("atime", MntFlags::MNT_NOATIME, true),
+    ("noowners", MntFlags::MNT_IGNORE_OWNERSHIP, false),
+    ("owners", MntFlags::MNT_IGNORE_OWNERSHIP, true),
+    ("nobrowse", MntFlags::MNT_DONTBROWSE, false),
+    ("browse", MntFlags::MNT_DONTBROWSE, true),
+    ("quarantine", MntFlags::MNT_QUARANTINE, false),
+    ("union", MntFlags::MNT_UNION, false),
+    ("force", MntFlags::MNT_FORCE, false),
+    ("update", MntFlags::MNT_UPDATE, false),
+];
+
+/// Options implied by `defaults`.
+pub const DEFAULT_OPTIONS: &[&str] = &["rw", "suid", "dev", "exec", "noasync"];
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct MountOptions {
@@ -25,6 +58,14 @@ impl MountOptions {
         self
     }
 
+    pub fn source(&self) -> &OsStr {
+        &self.volume
+    }
+
+    pub fn target(&self) -> &OsStr {
+        &self.mount_point
+    }
+
     pub fn mount_point(&mut self, mount_point: OsString) -> &mut Self {
         self.mount_point = mount_point;
         self
@@ -39,12 +80,42 @@ impl MountOptions {
         self.data = data;
         self
     }
+
+    pub fn read_only(&mut self, read_only: bool) -> &mut Self {
+        self.flags.set(MntFlags::MNT_RDONLY, read_only);
+        self
+    }
+
+    pub fn no_suid(&mut self, no_suid: bool) -> &mut Self {
+        self.flags.set(MntFlags::MNT_NOSUID, no_suid);
+        self
+    }
+
+    pub fn no_dev(&mut self, no_dev: bool) -> &mut Self {
+        self.flags.set(MntFlags::MNT_NODEV, no_dev);
+        self
+    }
+
+    pub fn no_exec(&mut self, no_exec: bool) -> &mut Self {
+        self.flags.set(MntFlags::MNT_NOEXEC, no_exec);
+        self
+    }
+
+    pub fn no_atime(&mut self, no_atime: bool) -> &mut Self {
+        self.flags.set(MntFlags::MNT_NOATIME, no_atime);
+        self
+    }
+
+    pub fn relatime(&mut self, _: bool) -> &mut Self {
+        self
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct UnmountOptions {
     mount_point: OsString,
     flags: MntFlags,
+    lazy: bool,
 }
 
 impl UnmountOptions {
@@ -52,14 +123,29 @@ impl UnmountOptions {
         Self {
             mount_point: OsString::new(),
             flags: MntFlags::empty(),
+            lazy: false,
         }
     }
 
+    pub fn target(&self) -> &OsStr {
+        &self.mount_point
+    }
+
     pub fn mount_point(&mut self, mount_point: OsString) -> &mut Self {
         self.mount_point = mount_point;
         self
     }
 
+    pub fn force(&mut self, force: bool) -> &mut Self {
+        self.flags.set(MntFlags::MNT_FORCE, force);
+        self
+    }
+
+    pub fn lazy(&mut self, lazy: bool) -> &mut Self {
+        self.lazy = lazy;
+        self
+    }
+
     pub fn flags(&mut self, flags: MntFlags) -> &mut Self {
         self.flags = flags;
         self
@@ -78,6 +164,30 @@ pub fn mount(options: &MountOptions) -> Result<()> {
 }
 
 pub fn unmount(options: &UnmountOptions) -> Result<()> {
+    if options.lazy {
+        return Err(io::Error::from(io::ErrorKind::Unsupported).into());
+    }
+
     mount::unmount(options.mount_point.as_os_str(), options.flags)?;
     Ok(())
 }
+
+pub fn bind(_: &BindOptions) -> Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported).into())
+}
+
+pub fn remount(_: &RemountOptions) -> Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported).into())
+}
+
+pub fn move_mount(_: &OsStr, _: &OsStr) -> Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported).into())
+}
+
+pub fn replace(_: &OsStr, _: &OsStr) -> Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported).into())
+}
+
+pub fn unmount_all(_: &UnmountOptions) -> Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported).into())
+}
@@ -1,6 +1,59 @@
+use crate::mount::{BindOptions, RemountOptions};
 use crate::Result;
+use nix::errno::Errno;
+use nix::libc;
 use nix::mount::{self, MntFlags};
-use std::ffi::OsString;
+use std::ffi::{CStr, CString, OsStr, OsString};
+use std::io;
+use std::mem;
+use std::os::unix::ffi::OsStrExt;
+
+pub type Flags = MntFlags;
+
+/// Options of `mount(8)` that map to a flag. The last field is `true` when
+/// the option clears the flag instead of setting it.
+pub const FLAG_OPTIONS: &[(&str, MntFlags, bool)] = &[
+    ("ro", MntFlags::MNT_RDONLY, false),
+    ("rw", MntFlags::MNT_RDONLY, true),
+    ("nosuid", MntFlags::MNT_NOSUID, false),
+    ("suid", MntFlags::MNT_NOSUID, true),
+    ("noexec", MntFlags::MNT_NOEXEC, false),
+    ("exec", MntFlags::MNT_NOEXEC, true),
+    ("sync", MntFlags::MNT_SYNCHRONOUS, false),
+    ("async", MntFlags::MNT_ASYNC, false),
+    ("noasync", MntFlags::MNT_ASYNC, true),
+    ("noatime", MntFlags::MNT_NOATIME, false),
+    ("atime", MntFlags::MNT_NOATIME, true),
+    ("noclusterr", MntFlags::MNT_NOCLUSTERR, false),
+    ("noclusterw", MntFlags::MNT_NOCLUSTERW, false),
+    ("nosymfollow", MntFlags::MNT_NOSYMFOLLOW, false),
+    ("suiddir", MntFlags::MNT_SUIDDIR, false),
+    ("multilabel", MntFlags::MNT_MULTILABEL, false),
+    ("acls", MntFlags::MNT_ACLS, false),
+    ("nfsv4acls", MntFlags::MNT_NFS4ACLS, false),
+    ("union", MntFlags::MNT_UNION, false),
+    ("snapshot", MntFlags::MNT_SNAPSHOT, false),
+    ("force", MntFlags::MNT_FORCE, false),
+    ("update", MntFlags::MNT_UPDATE, false),
+];
+
+/// Options implied by `defaults`.
+pub const DEFAULT_OPTIONS: &[&str] = &["rw", "suid", "exec", "noasync"];
+
+/// Flags of a mounted file system that are kept when it is updated.
+const UPDATE_FLAGS: MntFlags = MntFlags::MNT_RDONLY
+    .union(MntFlags::MNT_NOSUID)
+    .union(MntFlags::MNT_NOEXEC)
+    .union(MntFlags::MNT_SYNCHRONOUS)
+    .union(MntFlags::MNT_ASYNC)
+    .union(MntFlags::MNT_NOATIME)
+    .union(MntFlags::MNT_NOCLUSTERR)
+    .union(MntFlags::MNT_NOCLUSTERW)
+    .union(MntFlags::MNT_NOSYMFOLLOW)
+    .union(MntFlags::MNT_SUIDDIR)
+    .union(MntFlags::MNT_MULTILABEL)
+    .union(MntFlags::MNT_ACLS)
+    .union(MntFlags::MNT_NFS4ACLS);
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct MountOptions {
@@ -27,6 +80,14 @@ impl MountOptions {
         self
     }
 
+    pub fn source(&self) -> &OsStr {
+        &self.volume
+    }
+
+    pub fn target(&self) -> &OsStr {
+        &self.mount_point
+    }
+
     pub fn mount_point(&mut self, mount_point: OsString) -> &mut Self {
         self.mount_point = mount_point;
         self
@@ -46,12 +107,41 @@ impl MountOptions {
         self.data = data;
         self
     }
+
+    pub fn read_only(&mut self, read_only: bool) -> &mut Self {
+        self.flags.set(MntFlags::MNT_RDONLY, read_only);
+        self
+    }
+
+    pub fn no_suid(&mut self, no_suid: bool) -> &mut Self {
+        self.flags.set(MntFlags::MNT_NOSUID, no_suid);
+        self
+    }
+
+    pub fn no_dev(&mut self, _: bool) -> &mut Self {
+        self
+    }
+
+    pub fn no_exec(&mut self, no_exec: bool) -> &mut Self {
+        self.flags.set(MntFlags::MNT_NOEXEC, no_exec);
+        self
+    }
+
+    pub fn no_atime(&mut self, no_atime: bool) -> &mut Self {
+        self.flags.set(MntFlags::MNT_NOATIME, no_atime);
+        self
+    }
+
+    pub fn relatime(&mut self, _: bool) -> &mut Self {
+        self
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct UnmountOptions {
     mount_point: OsString,
     flags: MntFlags,
+    lazy: bool,
 }
 
 impl UnmountOptions {
@@ -59,14 +149,29 @@ impl UnmountOptions {
         Self {
             mount_point: OsString::new(),
             flags: MntFlags::empty(),
+            lazy: false,
         }
     }
 
+    pub fn target(&self) -> &OsStr {
+        &self.mount_point
+    }
+
     pub fn mount_point(&mut self, mount_point: OsString) -> &mut Self {
         self.mount_point = mount_point;
         self
     }
 
+    pub fn force(&mut self, force: bool) -> &mut Self {
+        self.flags.set(MntFlags::MNT_FORCE, force);
+        self
+    }
+
+    pub fn lazy(&mut self, lazy: bool) -> &mut Self {
+        self.lazy = lazy;
+        self
+    }
+
     pub fn flags(&mut self, flags: MntFlags) -> &mut Self {
         self.flags = flags;
         self
@@ -74,6 +179,11 @@ impl UnmountOptions {
 }
 
 pub fn mount(options: &MountOptions) -> Result<()> {
+    nmount(options, None)
+}
+
+/// Mounts with `nmount(2)`, passing `option` as an extra option without a value.
+fn nmount(options: &MountOptions, option: Option<&str>) -> Result<()> {
     let mut mount = mount::Nmount::new();
 
     mount
@@ -84,12 +194,93 @@ pub fn mount(options: &MountOptions) -> Result<()> {
         mount.str_opt_owned("fstype", fstype.as_os_str());
     }
 
+    if let Some(option) = option {
+        mount.null_opt_owned(option);
+    }
+
     mount.nmount(options.flags).map_err(|e| e.error())?;
 
     Ok(())
 }
 
 pub fn unmount(options: &UnmountOptions) -> Result<()> {
+    if options.lazy {
+        return Err(io::Error::from(io::ErrorKind::Unsupported).into());
+    }
+
     mount::unmount(options.mount_point.as_os_str(), options.flags)?;
     Ok(())
 }
+
+pub fn bind(options: &BindOptions) -> Result<()> {
+    // nullfs never crosses into the mounts under its source
+    if options.recursive {
+        return Err(io::Error::from(io::ErrorKind::Unsupported).into());
+    }
+
+    let mut mount = MountOptions::new();
+    mount
+        .volume(options.source.clone())
+        .mount_point(options.target.clone())
+        .fs_type(Some("nullfs".into()))
+        .read_only(options.read_only);
+
+    self::mount(&mount)
+}
+
+pub fn remount(options: &RemountOptions) -> Result<()> {
+    let path = CString::new(options.mount_point.as_bytes()).map_err(|_| Errno::EINVAL)?;
+    let mut stat = mem::MaybeUninit::<libc::statfs>::uninit();
+    Errno::result(unsafe { libc::statfs(path.as_ptr(), stat.as_mut_ptr()) })?;
+    let stat = unsafe { stat.assume_init() };
+
+    let name = |field: &[libc::c_char]| {
+        let field = unsafe { CStr::from_ptr(field.as_ptr()) };
+        OsStr::from_bytes(field.to_bytes()).to_os_string()
+    };
+
+    let current = MntFlags::from_bits_truncate(stat.f_flags as libc::c_int);
+    let mut mount = MountOptions::new();
+    mount
+        .volume(name(&stat.f_mntfromname))
+        .mount_point(name(&stat.f_mntonname))
+        .fs_type(Some(name(&stat.f_fstypename)))
+        .flags(current & UPDATE_FLAGS | MntFlags::MNT_UPDATE);
+
+    let changes: [(
+        Option<bool>,
+        fn(&mut MountOptions, bool) -> &mut MountOptions,
+    ); 4] = [
+        (options.read_only, MountOptions::read_only),
+        (options.no_suid, MountOptions::no_suid),
+        (options.no_exec, MountOptions::no_exec),
+        (options.no_atime, MountOptions::no_atime),
+    ];
+
+    for (value, set) in changes {
+        if let Some(value) = value {
+            set(&mut mount, value);
+        }
+    }
+
+    // File systems such as ufs look for the option instead of the flag
+    let option = if mount.flags.contains(MntFlags::MNT_RDONLY) {
+        "ro"
+    } else {
+        "rw"
+    };
+
+    nmount(&mount, Some(option))
+}
+
+pub fn move_mount(_: &OsStr, _: &OsStr) -> Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported).into())
+}
+
+pub fn replace(_: &OsStr, _: &OsStr) -> Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported).into())
+}
+
+pub fn unmount_all(_: &UnmountOptions) -> Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported).into())
+}
@@ -0,0 +1,45 @@
+//! Changing mount attributes with `mount_setattr`. Check [mount_setattr(2)] for details.
+//!
+//! [mount_setattr(2)]: https://man7.org/linux/man-pages/man2/mount_setattr.2.html
+
+use super::syscall::{self, MountAttr};
+use super::tree::{self, cstring};
+use crate::Result;
+use nix::libc::{self, c_uint};
+use std::ffi::{CString, OsStr};
+use std::os::fd::{AsRawFd, OwnedFd};
+
+/// Changes the attributes of the mount at `path`.
+pub fn set(path: &OsStr, recursive: bool, attr: &MountAttr) -> Result<()> {
+    syscall::mount_setattr(libc::AT_FDCWD, &cstring(path)?, flags(recursive), attr)?;
+
+    Ok(())
+}
+
+/// Changes the attributes of the detached mount referred to by `mount`.
+pub fn set_detached(mount: &OwnedFd, recursive: bool, attr: &MountAttr) -> Result<()> {
+    syscall::mount_setattr(
+        mount.as_raw_fd(),
+        &CString::default(),
+        flags(recursive) | libc::AT_EMPTY_PATH as c_uint,
+        attr,
+    )?;
+
+    Ok(())
+}
+
+/// Creates a bind mount of `source` at `target` with `attr` applied before it is attached,
+/// so it is never visible without them.
+pub fn bind(source: &OsStr, target: &OsStr, recursive: bool, attr: &MountAttr) -> Result<()> {
+    let tree = tree::clone(source, recursive)?;
+    set_detached(&tree, recursive, attr)?;
+    tree::attach(&tree, libc::AT_FDCWD, target)
+}
+
+fn flags(recursive: bool) -> c_uint {
+    if recursive {
+        libc::AT_RECURSIVE as c_uint
+    } else {
+        0
+    }
+}
@@ -0,0 +1,141 @@
+//! Creating file systems through a file system context, using `fsopen`, `fsconfig` and
+//! `fsmount`. Check [fsopen(2)] for details.
+//!
+//! [fsopen(2)]: https://man7.org/linux/man-pages/man2/fsopen.2.html
+
+use super::syscall::{self, *};
+use super::tree::cstring;
+use super::MountOptions;
+use crate::mount::options::split;
+use crate::{Error, OsError, Result};
+use nix::errno::Errno;
+use nix::mount::MsFlags;
+use nix::unistd;
+use std::ffi::OsStr;
+use std::os::fd::{AsRawFd, OwnedFd, RawFd};
+
+/// Flags that can be expressed through a file system context. `MS_SILENT` has no
+/// parameter, so mounts asking for it go through `mount(2)`.
+const SUPPORTED_FLAGS: MsFlags = MsFlags::MS_RDONLY
+    .union(MsFlags::MS_NOSUID)
+    .union(MsFlags::MS_NODEV)
+    .union(MsFlags::MS_NOEXEC)
+    .union(MsFlags::MS_SYNCHRONOUS)
+    .union(MsFlags::MS_DIRSYNC)
+    .union(MsFlags::MS_MANDLOCK)
+    .union(MsFlags::MS_NOATIME)
+    .union(MsFlags::MS_NODIRATIME)
+    .union(MsFlags::MS_RELATIME)
+    .union(MsFlags::MS_STRICTATIME)
+    .union(MsFlags::MS_LAZYTIME);
+
+/// Superblock flags and the parameter that sets them.
+const SUPERBLOCK_FLAGS: &[(MsFlags, &str)] = &[
+    (MsFlags::MS_RDONLY, "ro"),
+    (MsFlags::MS_SYNCHRONOUS, "sync"),
+    (MsFlags::MS_DIRSYNC, "dirsync"),
+    (MsFlags::MS_MANDLOCK, "mand"),
+    (MsFlags::MS_LAZYTIME, "lazytime"),
+];
+
+/// Mount flags and the attribute that sets them.
+const MOUNT_ATTRIBUTES: &[(MsFlags, u64)] = &[
+    (MsFlags::MS_RDONLY, MOUNT_ATTR_RDONLY),
+    (MsFlags::MS_NOSUID, MOUNT_ATTR_NOSUID),
+    (MsFlags::MS_NODEV, MOUNT_ATTR_NODEV),
+    (MsFlags::MS_NOEXEC, MOUNT_ATTR_NOEXEC),
+    (MsFlags::MS_NOATIME, MOUNT_ATTR_NOATIME),
+    (MsFlags::MS_STRICTATIME, MOUNT_ATTR_STRICTATIME),
+    (MsFlags::MS_RELATIME, MOUNT_ATTR_RELATIME),
+    (MsFlags::MS_NODIRATIME, MOUNT_ATTR_NODIRATIME),
+];
+
+/// Returns `true` if `options` can be mounted through a file system context.
+pub fn supports(options: &MountOptions) -> bool {
+    options.fs_type.is_some() && SUPPORTED_FLAGS.contains(options.flags)
+}
+
+/// Creates a new file system and returns a detached mount of it.
+pub fn create(options: &MountOptions, volume: &OsStr) -> Result<OwnedFd> {
+    let fs_type = cstring(options.fs_type.as_deref().unwrap_or_default())?;
+    let context = syscall::fsopen(&fs_type, FSOPEN_CLOEXEC)?;
+    let fd = context.as_raw_fd();
+
+    configure(fd, options, volume).map_err(|errno| error(&context, errno))?;
+
+    let attributes = MOUNT_ATTRIBUTES
+        .iter()
+        .filter(|(flag, _)| options.flags.contains(*flag))
+        .fold(0, |attributes, (_, attribute)| attributes | attribute);
+
+    syscall::fsmount(fd, FSMOUNT_CLOEXEC, attributes).map_err(|errno| error(&context, errno))
+}
+
+fn configure(fd: RawFd, options: &MountOptions, volume: &OsStr) -> nix::Result<()> {
+    if !volume.is_empty() {
+        set_string(fd, "source", volume)?;
+    }
+
+    for (flag, name) in SUPERBLOCK_FLAGS {
+        if options.flags.contains(*flag) {
+            set_flag(fd, name)?;
+        }
+    }
+
+    let data = options
+        .data
+        .as_deref()
+        .unwrap_or_default()
+        .to_string_lossy();
+
+    for option in split(&data) {
+        match option.split_once('=') {
+            Some((key, value)) => {
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .unwrap_or(value);
+
+                set_string(fd, key, value.as_ref())?;
+            }
+            None => set_flag(fd, &option)?,
+        }
+    }
+
+    syscall::fsconfig(fd, FSCONFIG_CMD_CREATE, None, None, 0)
+}
+
+fn set_flag(fd: RawFd, key: &str) -> nix::Result<()> {
+    syscall::fsconfig(
+        fd,
+        FSCONFIG_SET_FLAG,
+        Some(&cstring(key.as_ref())?),
+        None,
+        0,
+    )
+}
+
+fn set_string(fd: RawFd, key: &str, value: &OsStr) -> nix::Result<()> {
+    let key = cstring(key.as_ref())?;
+    let value = cstring(value)?;
+    syscall::fsconfig(fd, FSCONFIG_SET_STRING, Some(&key), Some(&value), 0)
+}
+
+/// Builds an error out of `errno` and the messages logged by the kernel in the
+/// file system context.
+fn error(context: &OwnedFd, errno: Errno) -> Error {
+    let mut messages = Vec::new();
+    let mut buf = [0; 4096];
+
+    // Each read returns a single message, until there are no more
+    while let Ok(len @ 1..) = unistd::read(context, &mut buf) {
+        let message = String::from_utf8_lossy(&buf[..len]);
+        let message = message
+            .split_once(' ')
+            .map_or(&*message, |(_, message)| message);
+
+        messages.push(message.trim_end().to_string());
+    }
+
+    OsError::with_messages(errno as i32, messages).into()
+}
@@ -0,0 +1,539 @@
+pub(crate) mod attr;
+mod context;
+mod resolve;
+pub(crate) mod syscall;
+mod tree;
+pub(crate) mod userns;
+
+use crate::error::Operation;
+use crate::mount::{mounts, BindOptions, MountEntry, OptionString, RemountOptions};
+use crate::{Error, OsError, Result};
+use nix::errno::Errno;
+use nix::libc;
+use nix::mount::{self, MntFlags, MsFlags};
+use std::cmp::Reverse;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::os::fd::{OwnedFd, RawFd};
+use std::path::{Path, PathBuf};
+
+pub type Flags = MsFlags;
+
+/// Options of `mount(8)` that map to a flag. The last field is `true` when
+/// the option clears the flag instead of setting it.
+pub const FLAG_OPTIONS: &[(&str, MsFlags, bool)] = &[
+    ("ro", MsFlags::MS_RDONLY, false),
+    ("rw", MsFlags::MS_RDONLY, true),
+    ("nosuid", MsFlags::MS_NOSUID, false),
+    ("suid", MsFlags::MS_NOSUID, true),
+    ("nodev", MsFlags::MS_NODEV, false),
+    ("dev", MsFlags::MS_NODEV, true),
+    ("noexec", MsFlags::MS_NOEXEC, false),
+    ("exec", MsFlags::MS_NOEXEC, true),
+    ("sync", MsFlags::MS_SYNCHRONOUS, false),
+    ("async", MsFlags::MS_SYNCHRONOUS, true),
+    ("dirsync", MsFlags::MS_DIRSYNC, false),
+    ("mand", MsFlags::MS_MANDLOCK, false),
+    ("nomand", MsFlags::MS_MANDLOCK, true),
+    ("noatime", MsFlags::MS_NOATIME, false),
+    ("atime", MsFlags::MS_NOATIME, true),
+    ("nodiratime", MsFlags::MS_NODIRATIME, false),
+    ("diratime", MsFlags::MS_NODIRATIME, true),
+    ("relatime", MsFlags::MS_RELATIME, false),
+    ("norelatime", MsFlags::MS_RELATIME, true),
+    ("strictatime", MsFlags::MS_STRICTATIME, false),
+    ("nostrictatime", MsFlags::MS_STRICTATIME, true),
+    ("lazytime", MsFlags::MS_LAZYTIME, false),
+    ("nolazytime", MsFlags::MS_LAZYTIME, true),
+    ("iversion", MsFlags::MS_I_VERSION, false),
+    ("noiversion", MsFlags::MS_I_VERSION, true),
+    ("silent", MsFlags::MS_SILENT, false),
+    ("loud", MsFlags::MS_SILENT, true),
+    ("remount", MsFlags::MS_REMOUNT, false),
+    ("rbind", MsFlags::MS_BIND.union(MsFlags::MS_REC), false),
+    ("bind", MsFlags::MS_BIND, false),
+];
+
+/// Options implied by `defaults`.
+pub const DEFAULT_OPTIONS: &[&str] = &["rw", "suid", "dev", "exec", "async"];
+
+/// Which kernel interface is used to mount file systems.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MountApi {
+    /// Use `fsopen`, `fsconfig`, `fsmount` and `move_mount` when possible, falling back
+    /// to `mount` on kernels older than 5.2 or when the options can't be expressed with
+    /// them.
+    #[default]
+    Auto,
+    /// Only use `fsopen`, `fsconfig`, `fsmount` and `move_mount`.
+    New,
+    /// Only use `mount`.
+    Legacy,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MountOptions {
+    volume: OsString,
+    mount_point: OsString,
+    flags: MsFlags,
+    fs_type: Option<OsString>,
+    data: Option<OsString>,
+    api: MountApi,
+}
+
+impl MountOptions {
+    pub fn new() -> Self {
+        Self {
+            volume: OsString::new(),
+            mount_point: OsString::new(),
+            flags: MsFlags::empty(),
+            fs_type: None,
+            data: None,
+            api: MountApi::Auto,
+        }
+    }
+
+    pub fn volume(&mut self, device: OsString) -> &mut Self {
+        self.volume = device;
+        self
+    }
+
+    pub fn source(&self) -> &OsStr {
+        &self.volume
+    }
+
+    pub fn target(&self) -> &OsStr {
+        &self.mount_point
+    }
+
+    pub fn mount_point(&mut self, mount_point: OsString) -> &mut Self {
+        self.mount_point = mount_point;
+        self
+    }
+
+    pub fn flags(&mut self, flags: MsFlags) -> &mut Self {
+        self.flags = flags;
+        self
+    }
+
+    pub fn fs_type(&mut self, fs_type: Option<OsString>) -> &mut Self {
+        self.fs_type = fs_type;
+        self
+    }
+
+    pub fn data(&mut self, data: Option<OsString>) -> &mut Self {
+        self.data = data;
+        self
+    }
+
+    pub fn api(&mut self, api: MountApi) -> &mut Self {
+        self.api = api;
+        self
+    }
+
+    #[cfg(feature = "loopdev")]
+    pub fn is_read_only(&self) -> bool {
+        self.flags.contains(MsFlags::MS_RDONLY)
+    }
+
+    #[cfg(feature = "loopdev")]
+    pub fn has_fs_type(&self) -> bool {
+        self.fs_type.is_some()
+    }
+
+    pub fn read_only(&mut self, read_only: bool) -> &mut Self {
+        self.flags.set(MsFlags::MS_RDONLY, read_only);
+        self
+    }
+
+    pub fn no_suid(&mut self, no_suid: bool) -> &mut Self {
+        self.flags.set(MsFlags::MS_NOSUID, no_suid);
+        self
+    }
+
+    pub fn no_dev(&mut self, no_dev: bool) -> &mut Self {
+        self.flags.set(MsFlags::MS_NODEV, no_dev);
+        self
+    }
+
+    pub fn no_exec(&mut self, no_exec: bool) -> &mut Self {
+        self.flags.set(MsFlags::MS_NOEXEC, no_exec);
+        self
+    }
+
+    pub fn no_atime(&mut self, no_atime: bool) -> &mut Self {
+        self.flags.set(MsFlags::MS_NOATIME, no_atime);
+        self
+    }
+
+    pub fn relatime(&mut self, relatime: bool) -> &mut Self {
+        self.flags.set(MsFlags::MS_RELATIME, relatime);
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnmountOptions {
+    mount_point: OsString,
+    flags: MntFlags,
+}
+
+impl UnmountOptions {
+    pub fn new() -> Self {
+        Self {
+            mount_point: OsString::new(),
+            flags: MntFlags::empty(),
+        }
+    }
+
+    pub fn target(&self) -> &OsStr {
+        &self.mount_point
+    }
+
+    pub fn mount_point(&mut self, mount_point: OsString) -> &mut Self {
+        self.mount_point = mount_point;
+        self
+    }
+
+    pub fn force(&mut self, force: bool) -> &mut Self {
+        self.flags.set(MntFlags::MNT_FORCE, force);
+        self
+    }
+
+    pub fn lazy(&mut self, lazy: bool) -> &mut Self {
+        self.flags.set(MntFlags::MNT_DETACH, lazy);
+        self
+    }
+
+    pub fn flags(&mut self, flags: MntFlags) -> &mut Self {
+        self.flags = flags;
+        self
+    }
+}
+
+pub fn mount(options: &MountOptions) -> Result<()> {
+    mount_at(options, libc::AT_FDCWD, &options.mount_point)
+}
+
+/// Mounts at `path`, relative to `dirfd`. An empty `path` refers to `dirfd` itself.
+pub fn mount_at(options: &MountOptions, dirfd: RawFd, path: &OsStr) -> Result<()> {
+    let volume = resolve::resolve(&options.volume)?;
+
+    let new_api = match options.api {
+        MountApi::Auto => context::supports(options),
+        MountApi::New => true,
+        MountApi::Legacy => false,
+    };
+
+    if new_api {
+        match detached(options, &volume) {
+            Ok(detached) => return tree::attach(&detached, dirfd, path),
+            Err(e) if e.raw_os_error() == Some(libc::ENOSYS) && options.api == MountApi::Auto => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    // `mount` only takes paths, but the magic link of the directory in `/proc` still
+    // refers to the same directory, however the path to it changes
+    let target = if dirfd == libc::AT_FDCWD {
+        PathBuf::from(path)
+    } else {
+        Path::new(&format!("/proc/self/fd/{dirfd}")).join(path)
+    };
+
+    mount::mount(
+        Some(&*volume),
+        &target,
+        options.fs_type.as_deref(),
+        options.flags,
+        options.data.as_deref(),
+    )?;
+
+    Ok(())
+}
+
+pub fn attach(mount: &OwnedFd, dirfd: RawFd, path: &OsStr) -> Result<()> {
+    tree::attach(mount, dirfd, path)
+}
+
+pub fn clone_tree(path: &OsStr, recursive: bool) -> Result<OwnedFd> {
+    tree::clone(path, recursive)
+}
+
+pub fn mount_detached(options: &MountOptions) -> Result<OwnedFd> {
+    let volume = resolve::resolve(&options.volume)?;
+    detached(options, &volume)
+}
+
+fn detached(options: &MountOptions, volume: &OsStr) -> Result<OwnedFd> {
+    if !context::supports(options) {
+        return Err(Errno::EINVAL.into());
+    }
+
+    context::create(options, volume)
+}
+
+pub fn unmount(options: &UnmountOptions) -> Result<()> {
+    mount::umount2(options.mount_point.as_os_str(), options.flags)?;
+    Ok(())
+}
+
+pub fn unmount_all(options: &UnmountOptions) -> Result<()> {
+    let root = fs::canonicalize(&options.mount_point)?;
+    let mut pending: Vec<MountEntry> = mounts()?
+        .into_iter()
+        .filter(|entry| entry.mount_point().starts_with(&root))
+        .collect();
+
+    // Newer mounts first, so mounts hiding others go before them
+    pending.sort_by_key(|entry| Reverse(entry.id()));
+
+    let mut failures = Vec::new();
+
+    while !pending.is_empty() {
+        let index = pending
+            .iter()
+            .position(|entry| {
+                !pending
+                    .iter()
+                    .any(|child| child.parent_id() == entry.id() && child.id() != entry.id())
+            })
+            .unwrap_or_default();
+
+        let entry = pending.remove(index);
+
+        if let Err(errno) = mount::umount2(entry.mount_point(), options.flags) {
+            // Unmounting a parent may have already propagated to this mount
+            if mounts()?.iter().any(|mounted| mounted.id() == entry.id()) {
+                let error = Error::from(errno).context(Operation::Unmount, [entry.mount_point()]);
+                failures.push((entry.mount_point().to_path_buf(), error));
+            }
+        }
+    }
+
+    if failures.is_empty() {
+        return Ok(());
+    }
+
+    // Only busy mounts are left, so report the whole unmount as busy for it to be retried
+    // or forced like a single one
+    if failures
+        .iter()
+        .all(|(_, e)| matches!(e, Error::Busy { .. }))
+    {
+        let paths = failures.iter().map(|(path, _)| path.clone()).collect();
+        let source = failures.into_iter().find_map(|(_, e)| match e {
+            Error::Busy { source, .. } => source,
+            _ => None,
+        });
+
+        return Err(Error::Busy {
+            operation: "unmount",
+            paths,
+            source,
+        });
+    }
+
+    Err(Error::Unmount { failures })
+}
+
+pub fn umount(target: &OsStr) -> Result<()> {
+    mount::umount(target)?;
+    Ok(())
+}
+
+/// Flags a bind mount remount must keep, since the kernel refuses to clear them when the
+/// mount is locked, e.g. in a user namespace.
+const LOCKED_FLAGS: MsFlags = MsFlags::MS_NOSUID
+    .union(MsFlags::MS_NODEV)
+    .union(MsFlags::MS_NOEXEC)
+    .union(MsFlags::MS_NOATIME)
+    .union(MsFlags::MS_NODIRATIME)
+    .union(MsFlags::MS_RELATIME);
+
+/// Flags of the superblock, shown in the super options of the mount table.
+const SUPER_FLAGS: MsFlags = MsFlags::MS_RDONLY
+    .union(MsFlags::MS_SYNCHRONOUS)
+    .union(MsFlags::MS_DIRSYNC)
+    .union(MsFlags::MS_MANDLOCK)
+    .union(MsFlags::MS_LAZYTIME);
+
+pub fn bind(options: &BindOptions) -> Result<()> {
+    let mut flags = MsFlags::MS_BIND;
+    flags.set(MsFlags::MS_REC, options.recursive);
+
+    if !options.read_only {
+        mount::mount(
+            Some(options.source.as_os_str()),
+            options.target.as_os_str(),
+            None::<&OsStr>,
+            flags,
+            None::<&OsStr>,
+        )?;
+
+        return Ok(());
+    }
+
+    let attr = syscall::MountAttr {
+        attr_set: syscall::MOUNT_ATTR_RDONLY,
+        ..Default::default()
+    };
+
+    // Kernels with mount_setattr can make the detached copy read-only before attaching it
+    match attr::bind(
+        options.source.as_os_str(),
+        options.target.as_os_str(),
+        options.recursive,
+        &attr,
+    ) {
+        Err(Error::Unsupported { .. }) => {}
+        bound => return bound,
+    }
+
+    let target = fs::canonicalize(&options.target)?;
+    let existing: Vec<u32> = mounts()?.iter().map(MountEntry::id).collect();
+
+    mount::mount(
+        Some(options.source.as_os_str()),
+        options.target.as_os_str(),
+        None::<&OsStr>,
+        flags,
+        None::<&OsStr>,
+    )?;
+
+    // The read-only flag is ignored when creating a bind mount, so every new mount must
+    // be remounted. Undo the bind if that fails instead of leaving it writable.
+    if let Err(e) = remount_read_only(&target, &existing) {
+        let _ = mount::umount2(options.target.as_os_str(), MntFlags::MNT_DETACH);
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+/// Remounts read-only the mounts at or under `target` that are not in `existing`, which
+/// are the ones the bind mount created rather than any made meanwhile elsewhere.
+fn remount_read_only(target: &Path, existing: &[u32]) -> Result<()> {
+    let created = mounts()?
+        .into_iter()
+        .filter(|e| e.mount_point().starts_with(target) && !existing.contains(&e.id()));
+
+    for entry in created {
+        let options = OptionString::from(&*entry.options().to_string_lossy());
+        let flags = MsFlags::MS_REMOUNT
+            | MsFlags::MS_BIND
+            | MsFlags::MS_RDONLY
+            | options.flags() & LOCKED_FLAGS;
+
+        mount::mount(
+            None::<&OsStr>,
+            entry.mount_point(),
+            None::<&OsStr>,
+            flags,
+            None::<&OsStr>,
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn remount(options: &RemountOptions) -> Result<()> {
+    let target = fs::canonicalize(&options.mount_point)?;
+    let entry = mounts()?
+        .into_iter()
+        .rev()
+        .find(|entry| entry.mount_point() == target)
+        .ok_or_else(|| Error::NotMounted {
+            operation: "remount",
+            paths: vec![target.clone()],
+            source: None,
+        })?;
+
+    let current = OptionString::from(&*entry.options().to_string_lossy()).flags();
+    let mut flags = current & LOCKED_FLAGS;
+
+    // A remount of the file system resets the flags of its superblock, so the current ones
+    // must be passed again. The read-only flag then refers to the superblock too.
+    if options.mount_point_only {
+        flags |= current & MsFlags::MS_RDONLY;
+    } else {
+        let sb = OptionString::from(&*entry.super_options().to_string_lossy()).flags();
+        flags |= sb & SUPER_FLAGS;
+    }
+
+    let changes = [
+        (options.read_only, MsFlags::MS_RDONLY),
+        (options.no_suid, MsFlags::MS_NOSUID),
+        (options.no_dev, MsFlags::MS_NODEV),
+        (options.no_exec, MsFlags::MS_NOEXEC),
+        (options.no_atime, MsFlags::MS_NOATIME),
+        (options.relatime, MsFlags::MS_RELATIME),
+    ];
+
+    for (value, flag) in changes {
+        if let Some(value) = value {
+            flags.set(flag, value);
+        }
+    }
+
+    // Only one access time mode can be set, and the kernel keeps the current one when
+    // none is given, so going back to the default must be explicit.
+    if options.no_atime == Some(true) {
+        flags.remove(MsFlags::MS_RELATIME);
+    } else if options.relatime == Some(true) {
+        flags.remove(MsFlags::MS_NOATIME);
+    }
+
+    if (options.no_atime.is_some() || options.relatime.is_some())
+        && !flags.intersects(MsFlags::MS_NOATIME | MsFlags::MS_RELATIME)
+    {
+        flags.insert(MsFlags::MS_STRICTATIME);
+    }
+
+    flags.insert(MsFlags::MS_REMOUNT);
+    flags.set(MsFlags::MS_BIND, options.mount_point_only);
+
+    mount::mount(
+        None::<&OsStr>,
+        &target,
+        None::<&OsStr>,
+        flags,
+        None::<&OsStr>,
+    )?;
+
+    Ok(())
+}
+
+pub fn move_mount(from: &OsStr, to: &OsStr) -> Result<()> {
+    match tree::move_path(from, to, false) {
+        Err(Errno::ENOSYS) => {
+            mount::mount(
+                Some(from),
+                to,
+                None::<&OsStr>,
+                MsFlags::MS_MOVE,
+                None::<&OsStr>,
+            )?;
+
+            Ok(())
+        }
+        moved => Ok(moved?),
+    }
+}
+
+pub fn replace(source: &OsStr, target: &OsStr) -> Result<()> {
+    match tree::move_path(source, target, true) {
+        // Kernels older than 6.5 reject the flag. Moving on top instead would only hide
+        // the old mount, which is not what was asked for.
+        Err(errno @ (Errno::EINVAL | Errno::ENOSYS)) => Err(Error::Unsupported {
+            operation: "",
+            paths: Vec::new(),
+            source: Some(OsError::new(errno as i32)),
+        }),
+        moved => {
+            moved?;
+            mount::umount2(target, MntFlags::MNT_DETACH)?;
+            Ok(())
+        }
+    }
+}
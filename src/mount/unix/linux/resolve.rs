@@ -0,0 +1,186 @@
+use crate::{probe, Error, Result};
+use nix::libc;
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, OpenOptions};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Tag {
+    Uuid,
+    Label,
+    PartUuid,
+    PartLabel,
+}
+
+impl Tag {
+    fn parse(tag: &str) -> Option<Self> {
+        match tag {
+            "UUID" => Some(Tag::Uuid),
+            "LABEL" => Some(Tag::Label),
+            "PARTUUID" => Some(Tag::PartUuid),
+            "PARTLABEL" => Some(Tag::PartLabel),
+            _ => None,
+        }
+    }
+
+    fn directory(self) -> &'static str {
+        match self {
+            Tag::Uuid => "/dev/disk/by-uuid",
+            Tag::Label => "/dev/disk/by-label",
+            Tag::PartUuid => "/dev/disk/by-partuuid",
+            Tag::PartLabel => "/dev/disk/by-partlabel",
+        }
+    }
+
+    fn matches(self, expected: &str, found: &str) -> bool {
+        match self {
+            Tag::Uuid | Tag::PartUuid => expected.eq_ignore_ascii_case(found),
+            Tag::Label | Tag::PartLabel => expected == found,
+        }
+    }
+}
+
+/// Resolves a volume given as `UUID=`, `LABEL=`, `PARTUUID=` or `PARTLABEL=` to
+/// its device node. Any other volume is returned as is.
+///
+/// Links created by udev at `/dev/disk/by-*` are used when available. Otherwise, every
+/// block device is probed.
+pub fn resolve(volume: &OsStr) -> Result<Cow<'_, OsStr>> {
+    let Some((tag, value)) = volume.to_str().and_then(tagged) else {
+        return Ok(Cow::Borrowed(volume));
+    };
+
+    let device = match by_link(tag, value) {
+        Some(device) => Some(device),
+        None => scan(tag, value)?,
+    };
+
+    match device {
+        Some(device) => Ok(Cow::Owned(device.into_os_string())),
+        None => Err(Error::NotFound {
+            operation: "",
+            paths: vec![volume.into()],
+            source: None,
+        }),
+    }
+}
+
+/// Splits a tagged volume such as `LABEL="My Data"` into its tag and unquoted value.
+fn tagged(volume: &str) -> Option<(Tag, &str)> {
+    let (tag, value) = volume.split_once('=')?;
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value);
+
+    Some((Tag::parse(tag)?, value))
+}
+
+fn by_link(tag: Tag, value: &str) -> Option<PathBuf> {
+    let directory = Path::new(tag.directory());
+
+    [value.to_string(), value.to_lowercase()]
+        .iter()
+        .find_map(|name| fs::canonicalize(directory.join(encode(name))).ok())
+}
+
+fn scan(tag: Tag, value: &str) -> Result<Option<PathBuf>> {
+    for entry in fs::read_dir("/sys/class/block")? {
+        let name = entry?.file_name();
+        let device = Path::new("/dev").join(&name);
+
+        let found = match tag {
+            Tag::Uuid | Tag::Label => probe_device(&device).and_then(|probe| match tag {
+                Tag::Uuid => probe.uuid().map(Into::into),
+                _ => probe.label().map(Into::into),
+            }),
+            Tag::PartUuid | Tag::PartLabel => partition_info(&name, tag),
+        };
+
+        if found.is_some_and(|found| tag.matches(value, &found)) {
+            return Ok(Some(device));
+        }
+    }
+
+    Ok(None)
+}
+
+fn probe_device(device: &Path) -> Option<probe::Probe> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(device)
+        .ok()?;
+
+    probe::probe(&mut file).ok().flatten()
+}
+
+/// Reads the partition UUID or name the kernel found in the partition table.
+fn partition_info(name: &OsStr, tag: Tag) -> Option<String> {
+    let uevent =
+        fs::read_to_string(Path::new("/sys/class/block").join(name).join("uevent")).ok()?;
+    let key = match tag {
+        Tag::PartUuid => "PARTUUID=",
+        _ => "PARTNAME=",
+    };
+
+    uevent
+        .lines()
+        .find_map(|line| line.strip_prefix(key))
+        .map(String::from)
+}
+
+/// Encodes a tag value the way udev does when naming links in `/dev/disk/by-*`.
+fn encode(value: &str) -> OsString {
+    let mut encoded = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            c if c.is_ascii_alphanumeric() || "#+-.:=@_".contains(c) => encoded.push(c),
+            c if !c.is_ascii() => encoded.push(c),
+            c => encoded.push_str(&format!("\\x{:02x}", c as u8)),
+        }
+    }
+
+    encoded.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags() {
+        assert_eq!(tagged("UUID=3f2a-11"), Some((Tag::Uuid, "3f2a-11")));
+        assert_eq!(tagged("LABEL=\"My Data\""), Some((Tag::Label, "My Data")));
+        assert_eq!(tagged("PARTUUID=\"abc"), Some((Tag::PartUuid, "\"abc")));
+        assert_eq!(tagged("PARTLABEL="), Some((Tag::PartLabel, "")));
+        assert_eq!(tagged("uuid=3f2a-11"), None);
+        assert_eq!(tagged("/dev/sda1"), None);
+
+        assert!(Tag::Uuid.matches("3F2A-11", "3f2a-11"));
+        assert!(!Tag::Label.matches("DATA", "data"));
+    }
+
+    #[test]
+    fn encoding() {
+        assert_eq!(encode("My Data/1"), "My\\x20Data\\x2f1");
+        assert_eq!(encode("a#+-.:=@_z"), "a#+-.:=@_z");
+        assert_eq!(encode("données"), "données");
+    }
+
+    #[test]
+    fn unresolved() {
+        let volume = OsStr::new("LABEL=disket-test-missing-label");
+
+        match resolve(volume) {
+            Err(Error::NotFound { paths, .. }) => assert_eq!(paths, [Path::new(volume)]),
+            result => panic!("unexpected {result:?}"),
+        }
+
+        let device = resolve(OsStr::new("/dev/sda1")).unwrap();
+        assert!(matches!(device, Cow::Borrowed(device) if device == "/dev/sda1"));
+    }
+}
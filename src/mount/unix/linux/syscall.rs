@@ -0,0 +1,112 @@
+//! Wrappers for the mount syscalls added in Linux 5.2 and later, which are not available
+//! in nix yet. Check [mount_setattr(2)] and related pages for details.
+//!
+//! [mount_setattr(2)]: https://man7.org/linux/man-pages/man2/mount_setattr.2.html
+
+use nix::errno::Errno;
+use nix::libc::{self, c_int, c_uint};
+use std::ffi::CStr;
+use std::os::fd::{FromRawFd, OwnedFd, RawFd};
+use std::{mem, ptr};
+
+pub const FSOPEN_CLOEXEC: c_uint = 0x1;
+
+pub const FSCONFIG_SET_FLAG: c_uint = 0;
+pub const FSCONFIG_SET_STRING: c_uint = 1;
+pub const FSCONFIG_CMD_CREATE: c_uint = 6;
+
+pub const FSMOUNT_CLOEXEC: c_uint = 0x1;
+
+pub const MOVE_MOUNT_F_EMPTY_PATH: c_uint = 0x4;
+pub const MOVE_MOUNT_T_EMPTY_PATH: c_uint = 0x40;
+pub const MOVE_MOUNT_BENEATH: c_uint = 0x200;
+
+pub const OPEN_TREE_CLONE: c_uint = 0x1;
+pub const OPEN_TREE_CLOEXEC: c_uint = libc::O_CLOEXEC as c_uint;
+
+pub const MOUNT_ATTR_RDONLY: u64 = 0x1;
+pub const MOUNT_ATTR_NOSUID: u64 = 0x2;
+pub const MOUNT_ATTR_NODEV: u64 = 0x4;
+pub const MOUNT_ATTR_NOEXEC: u64 = 0x8;
+pub const MOUNT_ATTR_RELATIME: u64 = 0x0;
+pub const MOUNT_ATTR_NOATIME: u64 = 0x10;
+pub const MOUNT_ATTR_STRICTATIME: u64 = 0x20;
+pub const MOUNT_ATTR_ATIME: u64 = 0x70;
+pub const MOUNT_ATTR_NODIRATIME: u64 = 0x80;
+pub const MOUNT_ATTR_IDMAP: u64 = 0x100000;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MountAttr {
+    pub attr_set: u64,
+    pub attr_clr: u64,
+    pub propagation: u64,
+    pub userns_fd: u64,
+}
+
+pub fn fsopen(fs_type: &CStr, flags: c_uint) -> nix::Result<OwnedFd> {
+    let fd = unsafe { libc::syscall(libc::SYS_fsopen, fs_type.as_ptr(), flags) };
+    Errno::result(fd).map(|fd| unsafe { OwnedFd::from_raw_fd(fd as RawFd) })
+}
+
+pub fn fsconfig(
+    fd: RawFd,
+    cmd: c_uint,
+    key: Option<&CStr>,
+    value: Option<&CStr>,
+    aux: c_int,
+) -> nix::Result<()> {
+    let key = key.map_or(ptr::null(), CStr::as_ptr);
+    let value = value.map_or(ptr::null(), CStr::as_ptr);
+    let res = unsafe { libc::syscall(libc::SYS_fsconfig, fd, cmd, key, value, aux) };
+    Errno::result(res).map(drop)
+}
+
+pub fn fsmount(fd: RawFd, flags: c_uint, attr_flags: u64) -> nix::Result<OwnedFd> {
+    let fd = unsafe { libc::syscall(libc::SYS_fsmount, fd, flags, attr_flags as c_uint) };
+    Errno::result(fd).map(|fd| unsafe { OwnedFd::from_raw_fd(fd as RawFd) })
+}
+
+pub fn move_mount(
+    from_dirfd: RawFd,
+    from_path: &CStr,
+    to_dirfd: RawFd,
+    to_path: &CStr,
+    flags: c_uint,
+) -> nix::Result<()> {
+    let res = unsafe {
+        libc::syscall(
+            libc::SYS_move_mount,
+            from_dirfd,
+            from_path.as_ptr(),
+            to_dirfd,
+            to_path.as_ptr(),
+            flags,
+        )
+    };
+    Errno::result(res).map(drop)
+}
+
+pub fn open_tree(dirfd: RawFd, path: &CStr, flags: c_uint) -> nix::Result<OwnedFd> {
+    let fd = unsafe { libc::syscall(libc::SYS_open_tree, dirfd, path.as_ptr(), flags) };
+    Errno::result(fd).map(|fd| unsafe { OwnedFd::from_raw_fd(fd as RawFd) })
+}
+
+pub fn mount_setattr(
+    dirfd: RawFd,
+    path: &CStr,
+    flags: c_uint,
+    attr: &MountAttr,
+) -> nix::Result<()> {
+    let res = unsafe {
+        libc::syscall(
+            libc::SYS_mount_setattr,
+            dirfd,
+            path.as_ptr(),
+            flags,
+            attr as *const MountAttr,
+            mem::size_of::<MountAttr>(),
+        )
+    };
+    Errno::result(res).map(drop)
+}
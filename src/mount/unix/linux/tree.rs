@@ -0,0 +1,66 @@
+//! Detached mount trees, created with `open_tree` and attached with `move_mount`. Check
+//! [open_tree(2)] and [move_mount(2)] for details.
+//!
+//! [open_tree(2)]: https://man7.org/linux/man-pages/man2/open_tree.2.html
+//! [move_mount(2)]: https://man7.org/linux/man-pages/man2/move_mount.2.html
+
+use super::syscall::{
+    self, MOVE_MOUNT_BENEATH, MOVE_MOUNT_F_EMPTY_PATH, MOVE_MOUNT_T_EMPTY_PATH, OPEN_TREE_CLOEXEC,
+    OPEN_TREE_CLONE,
+};
+use crate::Result;
+use nix::errno::Errno;
+use nix::libc::{self, c_uint};
+use std::ffi::{CString, OsStr};
+use std::os::fd::{AsRawFd, OwnedFd, RawFd};
+use std::os::unix::ffi::OsStrExt;
+
+/// Clones the mount at `path`, and every mount under it if `recursive` is `true`, into
+/// a detached mount.
+pub fn clone(path: &OsStr, recursive: bool) -> Result<OwnedFd> {
+    let mut flags = OPEN_TREE_CLONE | OPEN_TREE_CLOEXEC;
+
+    if recursive {
+        flags |= libc::AT_RECURSIVE as c_uint;
+    }
+
+    Ok(syscall::open_tree(libc::AT_FDCWD, &cstring(path)?, flags)?)
+}
+
+/// Attaches a detached mount at `path`, relative to `dirfd`. An empty `path` refers to
+/// `dirfd` itself.
+pub fn attach(mount: &OwnedFd, dirfd: RawFd, path: &OsStr) -> Result<()> {
+    let mut flags = MOVE_MOUNT_F_EMPTY_PATH;
+
+    if path.is_empty() {
+        flags |= MOVE_MOUNT_T_EMPTY_PATH;
+    }
+
+    syscall::move_mount(
+        mount.as_raw_fd(),
+        &CString::default(),
+        dirfd,
+        &cstring(path)?,
+        flags,
+    )?;
+
+    Ok(())
+}
+
+/// Moves the mount at `from` to `to`. If `beneath` is `true`, the mount is placed under
+/// the mount at `to` instead of on top of it.
+pub fn move_path(from: &OsStr, to: &OsStr, beneath: bool) -> nix::Result<()> {
+    let flags = if beneath { MOVE_MOUNT_BENEATH } else { 0 };
+
+    syscall::move_mount(
+        libc::AT_FDCWD,
+        &cstring(from)?,
+        libc::AT_FDCWD,
+        &cstring(to)?,
+        flags,
+    )
+}
+
+pub fn cstring(s: &OsStr) -> nix::Result<CString> {
+    CString::new(s.as_bytes()).map_err(|_| Errno::EINVAL)
+}
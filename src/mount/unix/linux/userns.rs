@@ -0,0 +1,96 @@
+//! Creating user namespaces to be used by id-mapped mounts.
+
+use crate::Result;
+use nix::errno::Errno;
+use nix::libc;
+use std::fs::{self, File};
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+
+/// Creates a user namespace with the given uid and gid maps, in the format of
+/// `/proc/<pid>/uid_map`, and returns a file descriptor that refers to it.
+///
+/// A child process is forked to create the namespace, since the calling process can't
+/// leave its own. It exits once the namespace is opened.
+pub fn create(uid_map: &str, gid_map: &str) -> Result<OwnedFd> {
+    let (ready_read, ready_write) = pipe()?;
+    let (done_read, done_write) = pipe()?;
+
+    let pid = unsafe { libc::fork() };
+
+    if pid == 0 {
+        // Only async-signal-safe calls are allowed in the child of a forked process
+        unsafe {
+            // Otherwise the child keeps its own end open and never sees the parent closing it
+            libc::close(done_write.as_raw_fd());
+            libc::close(ready_read.as_raw_fd());
+
+            let errno = if libc::unshare(libc::CLONE_NEWUSER) == 0 {
+                0
+            } else {
+                Errno::last_raw()
+            };
+
+            libc::write(
+                ready_write.as_raw_fd(),
+                &errno as *const i32 as *const libc::c_void,
+                mem::size_of::<i32>(),
+            );
+
+            // Wait until the parent is done with the namespace
+            let mut byte = 0u8;
+            libc::read(
+                done_read.as_raw_fd(),
+                &mut byte as *mut u8 as *mut libc::c_void,
+                1,
+            );
+
+            libc::_exit(0);
+        }
+    }
+
+    Errno::result(pid)?;
+    drop(ready_write);
+    drop(done_read);
+
+    let result = configure(pid, &ready_read, uid_map, gid_map);
+
+    drop(done_write);
+    unsafe { libc::waitpid(pid, std::ptr::null_mut(), 0) };
+
+    result
+}
+
+fn configure(pid: libc::pid_t, ready: &OwnedFd, uid_map: &str, gid_map: &str) -> Result<OwnedFd> {
+    let mut errno = 0i32;
+    let len = unsafe {
+        libc::read(
+            ready.as_raw_fd(),
+            &mut errno as *mut i32 as *mut libc::c_void,
+            mem::size_of::<i32>(),
+        )
+    };
+
+    if len as usize != mem::size_of::<i32>() {
+        return Err(Errno::ECHILD.into());
+    }
+
+    if errno != 0 {
+        return Err(Errno::from_raw(errno).into());
+    }
+
+    let proc = format!("/proc/{pid}");
+    fs::write(format!("{proc}/uid_map"), uid_map)?;
+    // Required before writing a gid map without CAP_SETGID in the parent namespace
+    let _ = fs::write(format!("{proc}/setgroups"), "deny");
+    fs::write(format!("{proc}/gid_map"), gid_map)?;
+
+    Ok(File::open(format!("{proc}/ns/user"))?.into())
+}
+
+fn pipe() -> Result<(OwnedFd, OwnedFd)> {
+    let mut fds: [RawFd; 2] = [0; 2];
+    Errno::result(unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) })?;
+
+    Ok(unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) })
+}
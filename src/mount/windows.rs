@@ -1,6 +1,8 @@
 use crate::common::windows::Wide;
+use crate::mount::{BindOptions, RemountOptions};
 use crate::Result;
-use std::ffi::OsString;
+use std::ffi::{OsStr, OsString};
+use std::io;
 use windows::{core::PCWSTR, Win32::Storage::FileSystem};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -22,8 +24,40 @@ impl MountOptions {
         self
     }
 
+    pub fn source(&self) -> &OsStr {
+        &self.volume
+    }
+
+    pub fn target(&self) -> &OsStr {
+        &self.mount_point
+    }
+
     pub fn mount_point(&mut self, mount_point: OsString) -> &mut Self {
-        self.volume = mount_point;
+        self.mount_point = mount_point;
+        self
+    }
+
+    pub fn read_only(&mut self, _: bool) -> &mut Self {
+        self
+    }
+
+    pub fn no_suid(&mut self, _: bool) -> &mut Self {
+        self
+    }
+
+    pub fn no_dev(&mut self, _: bool) -> &mut Self {
+        self
+    }
+
+    pub fn no_exec(&mut self, _: bool) -> &mut Self {
+        self
+    }
+
+    pub fn no_atime(&mut self, _: bool) -> &mut Self {
+        self
+    }
+
+    pub fn relatime(&mut self, _: bool) -> &mut Self {
         self
     }
 }
@@ -31,19 +65,37 @@ impl MountOptions {
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct UnmountOptions {
     mount_point: OsString,
+    force: bool,
+    lazy: bool,
 }
 
 impl UnmountOptions {
     pub fn new() -> Self {
         Self {
             mount_point: OsString::new(),
+            force: false,
+            lazy: false,
         }
     }
 
+    pub fn target(&self) -> &OsStr {
+        &self.mount_point
+    }
+
     pub fn mount_point(&mut self, mount_point: OsString) -> &mut Self {
         self.mount_point = mount_point;
         self
     }
+
+    pub fn force(&mut self, force: bool) -> &mut Self {
+        self.force = force;
+        self
+    }
+
+    pub fn lazy(&mut self, lazy: bool) -> &mut Self {
+        self.lazy = lazy;
+        self
+    }
 }
 
 pub fn mount(options: &MountOptions) -> Result<()> {
@@ -58,6 +110,10 @@ pub fn mount(options: &MountOptions) -> Result<()> {
 }
 
 pub fn unmount(options: &UnmountOptions) -> Result<()> {
+    if options.force || options.lazy {
+        return Err(io::Error::from(io::ErrorKind::Unsupported).into());
+    }
+
     let volume = PCWSTR::from_raw(options.mount_point.wide().as_ptr());
 
     unsafe {
@@ -66,3 +122,23 @@ pub fn unmount(options: &UnmountOptions) -> Result<()> {
 
     Ok(())
 }
+
+pub fn bind(_: &BindOptions) -> Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported).into())
+}
+
+pub fn remount(_: &RemountOptions) -> Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported).into())
+}
+
+pub fn move_mount(_: &OsStr, _: &OsStr) -> Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported).into())
+}
+
+pub fn replace(_: &OsStr, _: &OsStr) -> Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported).into())
+}
+
+pub fn unmount_all(_: &UnmountOptions) -> Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported).into())
+}
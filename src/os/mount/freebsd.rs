@@ -4,6 +4,9 @@ use std::ffi::OsStr;
 /// FreeBSD specific extensions for [`crate::mount::MountOptions`]
 pub trait MountOptionsExt {
     /// Sets flags to modify the behaviour of `mount`.
+    ///
+    /// This replaces every flag previously set, including the ones set through portable
+    /// methods such as [`crate::mount::MountOptions::read_only`].
     fn flags(&mut self, flags: MntFlags) -> &mut Self;
     /// Sets the filesystem type.
     ///
@@ -1,11 +1,24 @@
-use crate::mount::unix::linux;
+use crate::error::Operation;
+use crate::mount::unix::linux::syscall::{
+    MountAttr, MOUNT_ATTR_ATIME, MOUNT_ATTR_IDMAP, MOUNT_ATTR_NOATIME, MOUNT_ATTR_NODEV,
+    MOUNT_ATTR_NOEXEC, MOUNT_ATTR_NOSUID, MOUNT_ATTR_RDONLY,
+};
+use crate::mount::unix::linux::{self, attr, userns};
 use crate::Result;
+pub use linux::MountApi;
+use nix::libc;
 pub use nix::mount::{MntFlags, MsFlags};
 use std::ffi::OsStr;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd};
+use std::path::PathBuf;
+use std::process;
 
 /// Linux specific extensions for [`crate::mount::MountOptions`]
 pub trait MountOptionsExt {
     /// Sets flags to modify the behaviour of `mount`.
+    ///
+    /// This replaces every flag previously set, including the ones set through portable
+    /// methods such as [`crate::mount::MountOptions::read_only`].
     fn flags(&mut self, flags: MsFlags) -> &mut Self;
     /// Sets the filesystem type.
     ///
@@ -15,6 +28,52 @@ pub trait MountOptionsExt {
     ///
     /// This is usually a comma-separated list.
     fn data<T: AsRef<OsStr>>(&mut self, data: Option<T>) -> &mut Self;
+    /// Sets which kernel interface is used to mount. Defaults to [`MountApi::Auto`].
+    ///
+    /// When mounting through `fsopen` and friends, errors carry the messages logged by the
+    /// kernel, such as the name of a rejected option, in their [`crate::OsError`] source.
+    fn api(&mut self, api: MountApi) -> &mut Self;
+    /// Creates the file system without attaching it anywhere.
+    ///
+    /// The mount point is ignored. This always uses `fsopen` and friends, regardless of
+    /// [`MountOptionsExt::api`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `EINVAL` if no file system type is set, or if the flags can't be expressed
+    /// with `fsopen` and friends, such as `MS_BIND` or `MS_REMOUNT`.
+    fn mount_detached(&self) -> Result<DetachedMount>;
+    /// Mounts at `path`, relative to the directory referred to by `dirfd`, instead of the
+    /// mount point. An empty `path` refers to the directory itself.
+    ///
+    /// Since the directory is held open, moving or replacing it, or any directory above it,
+    /// can't make the file system end up mounted somewhere else. Use this when the mount
+    /// point lives in a directory other users can write to.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # #[cfg(target_os = "linux")]
+    /// use disket::{mount::MountOptions, os::mount::linux::MountOptionsExt};
+    /// use std::error::Error;
+    /// use std::fs::File;
+    /// use std::os::fd::AsFd;
+    ///
+    /// # #[cfg(target_os = "linux")]
+    /// fn main() -> Result<(), Box<dyn Error>> {
+    ///     let home = File::open("/home/user")?;
+    ///
+    ///     MountOptions::new()
+    ///         .volume("tmpfs")
+    ///         .fs_type(Some("tmpfs"))
+    ///         .mount_at(home.as_fd(), "cache")?;
+    ///
+    ///     Ok(())
+    /// }
+    /// # #[cfg(not(target_os = "linux"))]
+    /// # fn main() {}
+    /// ```
+    fn mount_at<T: AsRef<OsStr>>(&self, dirfd: BorrowedFd<'_>, path: T) -> Result<()>;
 }
 
 impl MountOptionsExt for crate::mount::MountOptions {
@@ -33,6 +92,157 @@ impl MountOptionsExt for crate::mount::MountOptions {
         self.inner.data(data.map(|d| d.as_ref().to_os_string()));
         self
     }
+
+    fn api(&mut self, api: MountApi) -> &mut Self {
+        self.inner.api(api);
+        self
+    }
+
+    fn mount_detached(&self) -> Result<DetachedMount> {
+        Ok(DetachedMount {
+            fd: linux::mount_detached(&self.inner)
+                .map_err(|e| e.context(Operation::Mount, [self.inner.source()]))?,
+        })
+    }
+
+    fn mount_at<T: AsRef<OsStr>>(&self, dirfd: BorrowedFd<'_>, path: T) -> Result<()> {
+        let path = path.as_ref();
+        linux::mount_at(&self.inner, dirfd.as_raw_fd(), path)
+            .map_err(|e| e.context(Operation::Mount, [self.inner.source(), path]))
+    }
+}
+
+/// A mount that is not attached anywhere in the file system tree.
+///
+/// A detached mount is either a new file system, created with
+/// [`MountOptionsExt::mount_detached`], or a copy of existing mounts, created with
+/// [`DetachedMount::clone_tree`]. Its contents can be inspected through
+/// [`DetachedMount::path`] before attaching it with [`DetachedMount::attach`]. The mount
+/// is released if it is dropped before being attached.
+///
+/// Since a detached mount is just a file descriptor, it can be sent to a process in
+/// another mount namespace, e.g. over a unix socket, and attached there. Use
+/// [`OwnedFd::from`] and [`DetachedMount::from`] to convert between them.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(target_os = "linux")]
+/// use disket::{mount::MountOptions, os::mount::linux::MountOptionsExt};
+/// use std::error::Error;
+///
+/// # #[cfg(target_os = "linux")]
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     let mount = MountOptions::new()
+///         .volume("/dev/sdb1")
+///         .fs_type(Some("ext4"))
+///         .mount_detached()?;
+///
+///     if mount.path().join("etc/os-release").exists() {
+///         mount.attach("/mnt")?;
+///     }
+///
+///     Ok(())
+/// }
+/// # #[cfg(not(target_os = "linux"))]
+/// # fn main() {}
+/// ```
+#[derive(Debug)]
+pub struct DetachedMount {
+    fd: OwnedFd,
+}
+
+impl DetachedMount {
+    /// Returns a path that refers to the root of the mount, for as long as `self` lives.
+    ///
+    /// The path goes through `/proc`, so other processes can use it as well, as long as
+    /// they are allowed to access the file descriptors of this one.
+    pub fn path(&self) -> PathBuf {
+        PathBuf::from(format!(
+            "/proc/{}/fd/{}",
+            process::id(),
+            self.fd.as_raw_fd()
+        ))
+    }
+
+    /// Clones the mount at `path` into a detached mount. If `recursive` is `true`, every
+    /// mount under `path` is cloned as well.
+    ///
+    /// Requires Linux 5.2 or later. Check [open_tree(2)] for details.
+    ///
+    /// # Examples
+    ///
+    /// Make `/usr` available in a directory without any mount under it showing up
+    /// there first:
+    ///
+    /// ```no_run
+    /// # #[cfg(target_os = "linux")]
+    /// use disket::os::mount::linux::DetachedMount;
+    /// use std::error::Error;
+    ///
+    /// # #[cfg(target_os = "linux")]
+    /// fn main() -> Result<(), Box<dyn Error>> {
+    ///     DetachedMount::clone_tree("/usr", true)?.attach("/srv/sandbox/usr")?;
+    ///     Ok(())
+    /// }
+    /// # #[cfg(not(target_os = "linux"))]
+    /// # fn main() {}
+    /// ```
+    ///
+    /// [open_tree(2)]: https://man7.org/linux/man-pages/man2/open_tree.2.html
+    pub fn clone_tree<T: AsRef<OsStr>>(path: T, recursive: bool) -> Result<Self> {
+        Ok(Self {
+            fd: linux::clone_tree(path.as_ref(), recursive)
+                .map_err(|e| e.context("clone", [path.as_ref()]))?,
+        })
+    }
+
+    /// Attaches the mount at `target`.
+    pub fn attach<T: AsRef<OsStr>>(self, target: T) -> Result<()> {
+        let target = target.as_ref();
+        linux::attach(&self.fd, libc::AT_FDCWD, target).map_err(|e| e.context("attach", [target]))
+    }
+
+    /// Attaches the mount at `path`, relative to the directory referred to by `dirfd`.
+    /// An empty `path` refers to the directory itself.
+    ///
+    /// See [`MountOptionsExt::mount_at`] for why this is useful.
+    pub fn attach_at<T: AsRef<OsStr>>(self, dirfd: BorrowedFd<'_>, path: T) -> Result<()> {
+        let path = path.as_ref();
+        linux::attach(&self.fd, dirfd.as_raw_fd(), path).map_err(|e| e.context("attach", [path]))
+    }
+
+    /// Changes the attributes of the mount, including whether it is id-mapped.
+    pub fn set_attributes(&self, attributes: &MountAttributes) -> Result<()> {
+        attr::set_detached(&self.fd, attributes.recursive, &attributes.attr())
+            .map_err(|e| e.context("set attributes of detached mount", None::<PathBuf>))
+    }
+}
+
+impl AsFd for DetachedMount {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl AsRawFd for DetachedMount {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl From<DetachedMount> for OwnedFd {
+    fn from(mount: DetachedMount) -> Self {
+        mount.fd
+    }
+}
+
+impl From<OwnedFd> for DetachedMount {
+    /// Wraps a file descriptor of a detached mount, such as one received from another
+    /// process.
+    fn from(fd: OwnedFd) -> Self {
+        Self { fd }
+    }
 }
 
 /// Linux specific extensions for [`crate::mount::UnmountOptions`]
@@ -52,5 +262,209 @@ impl UnmountOptionsExt for crate::mount::UnmountOptions {
 ///
 /// Unlike [`crate::mount::mount`], which is equivalent to the `umount2` syscall, this one takes no flags.
 pub fn umount<T: AsRef<OsStr>>(target: T) -> Result<()> {
-    linux::umount(target.as_ref())
+    let target = target.as_ref();
+    linux::umount(target).map_err(|e| e.context(Operation::Unmount, [target]))
+}
+
+/// Attributes to be changed on a mount, or on a whole tree of mounts, with
+/// `mount_setattr`.
+///
+/// Start by calling `new`, chain calls to set the attributes to be changed and then call
+/// `apply`. Attributes that are not set are kept as they are. Unlike
+/// [`crate::mount::RemountOptions`], changes are applied to every mount under the path at
+/// once when `recursive` is set.
+///
+/// Requires Linux 5.12 or later. Check [mount_setattr(2)] for details.
+///
+/// # Examples
+///
+/// Make a container root file system, and everything mounted under it, read-only:
+///
+/// ```no_run
+/// # #[cfg(target_os = "linux")]
+/// use disket::os::mount::linux::MountAttributes;
+/// use std::error::Error;
+///
+/// # #[cfg(target_os = "linux")]
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     MountAttributes::new()
+///         .recursive(true)
+///         .read_only(true)
+///         .no_suid(true)
+///         .apply("/run/container/rootfs")?;
+///
+///     Ok(())
+/// }
+/// # #[cfg(not(target_os = "linux"))]
+/// # fn main() {}
+/// ```
+///
+/// Bind mount a directory with every file owned by uid 0 shown as owned by uid 100000:
+///
+/// ```no_run
+/// # #[cfg(target_os = "linux")]
+/// use disket::os::mount::linux::{self, IdMap, MountAttributes};
+/// use std::error::Error;
+/// use std::os::fd::AsFd;
+///
+/// # #[cfg(target_os = "linux")]
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     let map = [IdMap::new(0, 100000, 65536)];
+///     let userns = linux::user_namespace(&map, &map)?;
+///
+///     MountAttributes::new()
+///         .user_namespace(userns.as_fd())
+///         .bind("/home/user/rootfs", "/run/container/rootfs")?;
+///
+///     Ok(())
+/// }
+/// # #[cfg(not(target_os = "linux"))]
+/// # fn main() {}
+/// ```
+///
+/// [mount_setattr(2)]: https://man7.org/linux/man-pages/man2/mount_setattr.2.html
+#[derive(Clone, Debug, Default)]
+pub struct MountAttributes<'fd> {
+    attr: MountAttr,
+    recursive: bool,
+    userns: Option<BorrowedFd<'fd>>,
+}
+
+impl<'fd> MountAttributes<'fd> {
+    /// Creates a new set of attributes that changes nothing.
+    pub fn new() -> Self {
+        MountAttributes::default()
+    }
+
+    /// Applies the changes to every mount under the path as well.
+    pub fn recursive(&mut self, recursive: bool) -> &mut Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Makes the mount read-only or writable.
+    pub fn read_only(&mut self, read_only: bool) -> &mut Self {
+        self.set(MOUNT_ATTR_RDONLY, read_only)
+    }
+
+    /// Changes whether set-user-ID and set-group-ID bits are ignored.
+    pub fn no_suid(&mut self, no_suid: bool) -> &mut Self {
+        self.set(MOUNT_ATTR_NOSUID, no_suid)
+    }
+
+    /// Changes whether access to device special files is denied.
+    pub fn no_dev(&mut self, no_dev: bool) -> &mut Self {
+        self.set(MOUNT_ATTR_NODEV, no_dev)
+    }
+
+    /// Changes whether execution of programs is denied.
+    pub fn no_exec(&mut self, no_exec: bool) -> &mut Self {
+        self.set(MOUNT_ATTR_NOEXEC, no_exec)
+    }
+
+    /// Changes whether access times are updated. When `false`, access times are updated
+    /// as with `relatime`.
+    pub fn no_atime(&mut self, no_atime: bool) -> &mut Self {
+        // Access time modes are a field rather than flags, so it is cleared as a whole
+        self.attr.attr_clr |= MOUNT_ATTR_ATIME;
+        self.attr.attr_set &= !MOUNT_ATTR_ATIME;
+
+        if no_atime {
+            self.attr.attr_set |= MOUNT_ATTR_NOATIME;
+        }
+
+        self
+    }
+
+    /// Makes the mount id-mapped, according to the uid and gid maps of the user
+    /// namespace referred to by `userns`.
+    ///
+    /// Only mounts that are not attached yet can be id-mapped. Use
+    /// [`MountAttributes::bind`], or apply the attributes to a [`DetachedMount`].
+    pub fn user_namespace(&mut self, userns: BorrowedFd<'fd>) -> &mut Self {
+        self.userns = Some(userns);
+        self
+    }
+
+    /// Changes the attributes of the mount at `path`.
+    pub fn apply<T: AsRef<OsStr>>(&self, path: T) -> Result<()> {
+        let path = path.as_ref();
+        attr::set(path, self.recursive, &self.attr())
+            .map_err(|e| e.context("set attributes of", [path]))
+    }
+
+    /// Creates a bind mount of `source` at `target` with the attributes applied before it
+    /// becomes visible. When `recursive` is set, every mount under `source` is bound as
+    /// well.
+    ///
+    /// Unlike [`crate::mount::BindOptions`], there is no moment where the new mount is
+    /// writable or not yet id-mapped.
+    pub fn bind<T: AsRef<OsStr>>(&self, source: T, target: T) -> Result<()> {
+        let (source, target) = (source.as_ref(), target.as_ref());
+        attr::bind(source, target, self.recursive, &self.attr())
+            .map_err(|e| e.context("bind", [source, target]))
+    }
+
+    fn set(&mut self, attribute: u64, enabled: bool) -> &mut Self {
+        if enabled {
+            self.attr.attr_set |= attribute;
+            self.attr.attr_clr &= !attribute;
+        } else {
+            self.attr.attr_clr |= attribute;
+            self.attr.attr_set &= !attribute;
+        }
+
+        self
+    }
+
+    fn attr(&self) -> MountAttr {
+        let mut attr = self.attr;
+
+        if let Some(userns) = self.userns {
+            attr.attr_set |= MOUNT_ATTR_IDMAP;
+            attr.userns_fd = userns.as_raw_fd() as u64;
+        }
+
+        attr
+    }
+}
+
+/// A range of ids mapped from a user namespace to its parent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IdMap {
+    /// First id inside the user namespace.
+    pub inside: u32,
+    /// First id in the parent user namespace that `inside` maps to.
+    pub outside: u32,
+    /// How many ids are mapped.
+    pub count: u32,
+}
+
+impl IdMap {
+    /// Creates a new map of `count` ids, starting at `inside`, to the ids starting at
+    /// `outside`.
+    pub fn new(inside: u32, outside: u32, count: u32) -> Self {
+        Self {
+            inside,
+            outside,
+            count,
+        }
+    }
+}
+
+/// Creates a user namespace with the given uid and gid maps, to be used with
+/// [`MountAttributes::user_namespace`].
+///
+/// A short-lived child process is forked to create the namespace. Check
+/// [user_namespaces(7)] for the rules on which maps are allowed.
+///
+/// [user_namespaces(7)]: https://man7.org/linux/man-pages/man7/user_namespaces.7.html
+pub fn user_namespace(uid_map: &[IdMap], gid_map: &[IdMap]) -> Result<OwnedFd> {
+    let format = |map: &[IdMap]| -> String {
+        map.iter()
+            .map(|m| format!("{} {} {}\n", m.inside, m.outside, m.count))
+            .collect()
+    };
+
+    userns::create(&format(uid_map), &format(gid_map))
 }
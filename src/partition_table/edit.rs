@@ -0,0 +1,650 @@
+use super::{gpt, invalid, mbr, Guid, Partition, PartitionTable, PartitionType, TableKind};
+use crate::{Error, Result};
+use std::io;
+
+/// Options describing a partition to add to a [`PartitionTable`].
+///
+/// Start by calling `new`, chain calls to set every option and then pass it to
+/// [`PartitionTable::add`]. Unset options get defaults, so an empty set of options adds a
+/// Linux partition filling the first free space of the disk.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PartitionOptions {
+    number: Option<u32>,
+    start: Option<u64>,
+    size: Option<u64>,
+    partition_type: Option<PartitionType>,
+    guid: Option<Guid>,
+    name: Option<String>,
+    attributes: u64,
+    logical: bool,
+}
+
+impl PartitionOptions {
+    /// Creates a new set of options with default values.
+    pub fn new() -> Self {
+        PartitionOptions::default()
+    }
+
+    /// Sets the number of the partition. By default the first free one is used.
+    ///
+    /// Ignored for logical partitions, which are numbered in disk order from 5.
+    pub fn number(&mut self, number: u32) -> &mut Self {
+        self.number = Some(number);
+        self
+    }
+
+    /// Sets the offset in bytes where the partition starts, rounded up to the alignment of
+    /// the table. By default the partition starts in the first free space large enough.
+    pub fn start(&mut self, start: u64) -> &mut Self {
+        self.start = Some(start);
+        self
+    }
+
+    /// Sets the size of the partition in bytes, rounded up to whole sectors. By default the
+    /// partition fills the free space it starts in.
+    pub fn size(&mut self, size: u64) -> &mut Self {
+        self.size = Some(size);
+        self
+    }
+
+    /// Sets the type of the partition, which must suit the table. Defaults to Linux file
+    /// system: [`Guid::LINUX_FILESYSTEM`] for GPT and `0x83` for MBR.
+    pub fn partition_type(&mut self, partition_type: PartitionType) -> &mut Self {
+        self.partition_type = Some(partition_type);
+        self
+    }
+
+    /// Sets the unique GUID of a GPT partition. A random one is used by default.
+    pub fn guid(&mut self, guid: Guid) -> &mut Self {
+        self.guid = Some(guid);
+        self
+    }
+
+    /// Sets the name of a GPT partition, up to 36 UTF-16 code units long.
+    pub fn name<T: Into<String>>(&mut self, name: T) -> &mut Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Sets the GPT attribute flags, as described in [`Partition::attributes`]. Only the
+    /// bootable flag is kept for MBR partitions.
+    pub fn attributes(&mut self, attributes: u64) -> &mut Self {
+        self.attributes = attributes;
+        self
+    }
+
+    /// Marks the partition as bootable by legacy BIOS, which is the boot indicator of MBR
+    /// partitions.
+    pub fn bootable(&mut self, bootable: bool) -> &mut Self {
+        if bootable {
+            self.attributes |= gpt::LEGACY_BIOS_BOOTABLE;
+        } else {
+            self.attributes &= !gpt::LEGACY_BIOS_BOOTABLE;
+        }
+
+        self
+    }
+
+    /// Places the partition in the extended partition of an MBR partition table.
+    pub fn logical(&mut self, logical: bool) -> &mut Self {
+        self.logical = logical;
+        self
+    }
+}
+
+impl PartitionTable {
+    /// Adds a partition described by `options` and returns its number.
+    ///
+    /// The partition starts at the first aligned sector of the free space it goes in.
+    /// Adding a logical partition renumbers the logical partitions after it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`io::ErrorKind::InvalidInput`] error if the options don't suit the kind
+    /// of the table, if the number is taken or if there is not enough free space.
+    ///
+    /// # Examples
+    ///
+    /// Create a GPT image with an EFI system partition and a root partition filling the
+    /// rest of the disk:
+    ///
+    /// ```no_run
+    /// # #[cfg(feature = "device")]
+    /// use disket::device::partition_table::{
+    ///     Guid, PartitionOptions, PartitionTable, PartitionType, TableKind,
+    /// };
+    /// use std::error::Error;
+    /// use std::fs::File;
+    ///
+    /// # #[cfg(feature = "device")]
+    /// fn main() -> Result<(), Box<dyn Error>> {
+    ///     File::create("disk.img")?.set_len(8 << 30)?;
+    ///
+    ///     let mut table = PartitionTable::new(TableKind::Gpt, 8 << 30, 512)?;
+    ///     table.add(
+    ///         PartitionOptions::new()
+    ///             .size(512 << 20)
+    ///             .partition_type(PartitionType::Gpt(Guid::EFI_SYSTEM))
+    ///             .name("esp"),
+    ///     )?;
+    ///     table.add(PartitionOptions::new().name("root"))?;
+    ///     table.write("disk.img")?;
+    ///
+    ///     Ok(())
+    /// }
+    /// # #[cfg(not(feature = "device"))]
+    /// # fn main() {}
+    /// ```
+    pub fn add(&mut self, options: &PartitionOptions) -> Result<u32> {
+        let partition_type = match (self.kind, options.partition_type) {
+            (TableKind::Gpt, None) => PartitionType::Gpt(Guid::LINUX_FILESYSTEM),
+            (TableKind::Mbr, None) => PartitionType::Mbr(0x83),
+            (TableKind::Gpt, Some(t @ PartitionType::Gpt(_)))
+            | (TableKind::Mbr, Some(t @ PartitionType::Mbr(_))) => t,
+            _ => {
+                return Err(invalid(format!(
+                    "partition type does not suit a {:?} partition table",
+                    self.kind
+                )))
+            }
+        };
+
+        let is_extended =
+            matches!(partition_type, PartitionType::Mbr(kind) if mbr::is_extended(kind));
+        let extended = mbr::extended(&self.partitions);
+
+        match self.kind {
+            TableKind::Gpt if options.logical => {
+                return Err(invalid("GPT has no logical partitions".into()))
+            }
+            TableKind::Mbr if options.name.is_some() || options.guid.is_some() => {
+                return Err(invalid("MBR partitions have no name nor GUID".into()))
+            }
+            TableKind::Mbr if options.logical && (extended.is_none() || is_extended) => {
+                return Err(invalid(
+                    "logical partitions need an extended partition to go in".into(),
+                ))
+            }
+            TableKind::Mbr if is_extended && extended.is_some() => {
+                return Err(invalid("there is already an extended partition".into()))
+            }
+            _ => {}
+        }
+
+        if let Some(name) = &options.name {
+            check_name(name)?;
+        }
+
+        // Logical partitions get their number once sorted
+        let number = match options.logical {
+            true => u32::MAX,
+            false => self.free_number(options.number)?,
+        };
+
+        let (start_lba, end_lba) = self.place(options.start, options.size, options.logical)?;
+        let is_gpt = self.kind == TableKind::Gpt;
+
+        let mut partitions = self.partitions.clone();
+        partitions.push(Partition {
+            number,
+            start_lba,
+            end_lba,
+            sector_size: self.sector_size,
+            partition_type,
+            guid: is_gpt.then(|| options.guid.unwrap_or_else(Guid::random)),
+            name: is_gpt.then(|| options.name.clone().unwrap_or_default()),
+            attributes: match self.kind {
+                TableKind::Gpt => options.attributes,
+                TableKind::Mbr => options.attributes & gpt::LEGACY_BIOS_BOOTABLE,
+            },
+            boot_record: None,
+        });
+
+        renumber(&mut partitions);
+        self.check(&partitions)?;
+        self.partitions = partitions;
+
+        let added = self.partitions.iter().find(|p| p.start_lba == start_lba);
+
+        Ok(added.map_or(number, |p| p.number))
+    }
+
+    /// Deletes the partition with the given number.
+    ///
+    /// Deleting an extended partition deletes its logical partitions as well. Deleting a
+    /// logical partition renumbers the logical partitions after it, as the kernel numbers
+    /// them in disk order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`io::ErrorKind::NotFound`] error if there is no such partition.
+    pub fn delete(&mut self, number: u32) -> Result<()> {
+        let index = self.index(number)?;
+        let deleted = self.partitions.remove(index);
+
+        if mbr::extended(std::slice::from_ref(&deleted)).is_some() {
+            self.partitions.retain(|p| !mbr::is_logical(p));
+        }
+
+        renumber(&mut self.partitions);
+
+        Ok(())
+    }
+
+    /// Resizes the partition with the given number to `size` bytes, rounded up to whole
+    /// sectors. The partition keeps its start.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`io::ErrorKind::NotFound`] error if there is no such partition, or an
+    /// [`io::ErrorKind::InvalidInput`] error if the partition would overlap another one or
+    /// not fit in the disk.
+    pub fn resize(&mut self, number: u32, size: u64) -> Result<()> {
+        let index = self.index(number)?;
+        let sectors = self.sectors(size)?;
+
+        let mut partitions = self.partitions.clone();
+        partitions[index].end_lba = partitions[index].start_lba + sectors - 1;
+
+        self.check(&partitions)?;
+        self.partitions = partitions;
+
+        Ok(())
+    }
+
+    /// Renames the GPT partition with the given number.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`io::ErrorKind::NotFound`] error if there is no such partition, or an
+    /// [`io::ErrorKind::InvalidInput`] error if the table is an MBR or the name is longer
+    /// than 36 UTF-16 code units.
+    pub fn rename(&mut self, number: u32, name: &str) -> Result<()> {
+        if self.kind != TableKind::Gpt {
+            return Err(invalid("MBR partitions have no name".into()));
+        }
+
+        check_name(name)?;
+
+        let index = self.index(number)?;
+        self.partitions[index].name = Some(name.to_string());
+
+        Ok(())
+    }
+
+    /// Checks that `partitions` fit in the disk and don't overlap.
+    pub(super) fn check(&self, partitions: &[Partition]) -> Result<()> {
+        if self.kind == TableKind::Gpt {
+            let entries_sectors =
+                (self.entries as u64 * gpt::ENTRY_SIZE as u64).div_ceil(self.sector_size);
+
+            if self.first_usable_lba < 2 + entries_sectors
+                || self.last_usable_lba + 1 + entries_sectors >= self.disk_sectors
+            {
+                return Err(invalid("partition table does not fit in the disk".into()));
+            }
+        }
+
+        let extended = mbr::extended(partitions);
+        let mut primary = Vec::new();
+        let mut logical = Vec::new();
+
+        for partition in partitions {
+            let number = partition.number;
+
+            if partition.start_lba > partition.end_lba {
+                return Err(invalid(format!("partition {number} ends before it starts")));
+            }
+
+            if self.kind == TableKind::Gpt && number > self.entries {
+                return Err(invalid(format!(
+                    "partition {number} is beyond the {} entries of the table",
+                    self.entries
+                )));
+            }
+
+            if mbr::is_logical(partition) {
+                // Logical partitions are preceded by their extended boot record
+                let fits = extended.is_some_and(|extended| {
+                    partition.start_lba > extended.start_lba
+                        && partition.end_lba <= extended.end_lba
+                });
+
+                if !fits {
+                    return Err(invalid(format!(
+                        "partition {number} does not fit in the extended partition"
+                    )));
+                }
+
+                logical.push((partition.start_lba - 1, partition));
+            } else {
+                if partition.start_lba < self.first_usable_lba
+                    || partition.end_lba > self.last_usable_lba
+                {
+                    return Err(invalid(format!(
+                        "partition {number} does not fit in the disk"
+                    )));
+                }
+
+                primary.push((partition.start_lba, partition));
+            }
+        }
+
+        for mut partitions in [primary, logical] {
+            partitions.sort_by_key(|(start, _)| *start);
+
+            for pair in partitions.windows(2) {
+                let [(_, first), (start, second)] = pair else {
+                    continue;
+                };
+
+                if *start <= first.end_lba {
+                    return Err(invalid(format!(
+                        "partition {} overlaps partition {}",
+                        second.number, first.number
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Finds where a new partition goes, returning its first and last sectors.
+    fn place(&self, start: Option<u64>, size: Option<u64>, logical: bool) -> Result<(u64, u64)> {
+        let grain = (self.alignment / self.sector_size).max(1);
+        let start = start.map(|start| start.div_ceil(self.sector_size));
+        let sectors = size.map(|size| self.sectors(size)).transpose()?;
+
+        // Logical partitions need a free sector before them for their extended boot record
+        let reserved = logical as u64;
+        let (low, high, mut used): (u64, u64, Vec<(u64, u64)>) = match logical {
+            true => {
+                let extended = mbr::extended(&self.partitions).expect("checked by add");
+                let used = self
+                    .partitions
+                    .iter()
+                    .filter(|p| mbr::is_logical(p))
+                    .map(|p| (p.start_lba - 1, p.end_lba))
+                    .collect();
+
+                (extended.start_lba, extended.end_lba, used)
+            }
+            false => {
+                let used = self
+                    .partitions
+                    .iter()
+                    .filter(|p| !mbr::is_logical(p))
+                    .map(|p| (p.start_lba, p.end_lba))
+                    .collect();
+
+                (self.first_usable_lba, self.last_usable_lba, used)
+            }
+        };
+
+        used.sort_unstable();
+
+        let mut free = Vec::new();
+        let mut next = low;
+
+        for (first, last) in used {
+            if first > next {
+                free.push((next, first - 1));
+            }
+
+            next = next.max(last + 1);
+        }
+
+        if next <= high {
+            free.push((next, high));
+        }
+
+        for (first, last) in free {
+            let begin = (start.unwrap_or(first).max(first + reserved)).div_ceil(grain) * grain;
+
+            if start.is_some_and(|start| start.div_ceil(grain) * grain != begin) {
+                continue;
+            }
+
+            let end = match sectors {
+                Some(sectors) => begin + sectors - 1,
+                None => last,
+            };
+
+            if begin <= end && end <= last {
+                return Ok((begin, end));
+            }
+        }
+
+        Err(invalid(match start {
+            Some(start) => format!(
+                "no free space for the partition at sector {}",
+                start.div_ceil(grain) * grain
+            ),
+            None => "not enough free space for the partition".into(),
+        }))
+    }
+
+    /// Returns the first partition number not in use.
+    fn free_number(&self, number: Option<u32>) -> Result<u32> {
+        let max = match self.kind {
+            TableKind::Gpt => self.entries,
+            TableKind::Mbr => 4,
+        };
+        let taken = |number| self.partitions.iter().any(|p| p.number == number);
+
+        match number {
+            Some(number) if number == 0 || number > max => Err(invalid(format!(
+                "partition number {number} is not between 1 and {max}"
+            ))),
+            Some(number) if taken(number) => {
+                Err(invalid(format!("partition {number} already exists")))
+            }
+            Some(number) => Ok(number),
+            None => (1..=max)
+                .find(|&number| !taken(number))
+                .ok_or_else(|| invalid("no partition number left".into())),
+        }
+    }
+
+    fn index(&self, number: u32) -> Result<usize> {
+        self.partitions
+            .iter()
+            .position(|p| p.number == number)
+            .ok_or_else(|| {
+                Error::from(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("partition {number} not found"),
+                ))
+            })
+    }
+
+    /// Converts a size in bytes to a number of sectors, rounding up.
+    fn sectors(&self, size: u64) -> Result<u64> {
+        match size {
+            0 => Err(invalid("partition size can't be 0".into())),
+            size => Ok(size.div_ceil(self.sector_size)),
+        }
+    }
+}
+
+/// Sorts partitions by number, numbering logical partitions in disk order.
+fn renumber(partitions: &mut [Partition]) {
+    partitions.sort_by_key(|p| match mbr::is_logical(p) {
+        true => (true, p.start_lba),
+        false => (false, p.number as u64),
+    });
+
+    for (partition, number) in partitions
+        .iter_mut()
+        .filter(|p| mbr::is_logical(p))
+        .zip(5..)
+    {
+        partition.number = number;
+    }
+}
+
+fn check_name(name: &str) -> Result<()> {
+    match name.encode_utf16().count() {
+        length if length > gpt::NAME_LENGTH => Err(invalid(format!(
+            "partition name {name:?} is longer than {} characters",
+            gpt::NAME_LENGTH
+        ))),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const DISK_SIZE: u64 = 16 << 20;
+    const MIB: u64 = 1 << 20;
+
+    fn spans(table: &PartitionTable) -> Vec<(u32, u64, u64)> {
+        table
+            .partitions()
+            .iter()
+            .map(|p| (p.number(), p.start_lba(), p.end_lba()))
+            .collect()
+    }
+
+    fn round_trip(table: &PartitionTable) -> (PartitionTable, Vec<u8>) {
+        let mut disk = Cursor::new(vec![0; DISK_SIZE as usize]);
+        table.write_to(&mut disk).unwrap();
+
+        let read = PartitionTable::read(&mut disk).unwrap().unwrap();
+
+        (read, disk.into_inner())
+    }
+
+    fn kind(error: Error) -> io::ErrorKind {
+        io::Error::from(error).kind()
+    }
+
+    #[test]
+    fn gpt_edits() {
+        let mut table = PartitionTable::new(TableKind::Gpt, DISK_SIZE, 512).unwrap();
+
+        for _ in 0..3 {
+            table.add(PartitionOptions::new().size(2 * MIB)).unwrap();
+        }
+
+        table.delete(2).unwrap();
+        assert_eq!(table.add(PartitionOptions::new().size(MIB)).unwrap(), 2);
+
+        let error = table.resize(1, 3 * MIB).unwrap_err();
+        assert_eq!(kind(error), io::ErrorKind::InvalidInput);
+        table.resize(3, MIB).unwrap();
+
+        table.rename(3, "data").unwrap();
+        assert!(table.rename(3, &"x".repeat(37)).is_err());
+        assert_eq!(kind(table.delete(4).unwrap_err()), io::ErrorKind::NotFound);
+
+        assert_eq!(
+            spans(&table),
+            [(1, 2048, 6143), (2, 6144, 8191), (3, 10240, 12287)]
+        );
+
+        let (read, _) = round_trip(&table);
+        assert_eq!(read, table);
+        assert_eq!(read.partition(3).unwrap().name(), Some("data"));
+    }
+
+    #[test]
+    fn alignment() {
+        let mut table = PartitionTable::new(TableKind::Gpt, DISK_SIZE, 512).unwrap();
+        table
+            .add(PartitionOptions::new().start(1_000_000).size(MIB))
+            .unwrap();
+        table.set_alignment(512);
+        table.add(PartitionOptions::new().size(MIB / 2)).unwrap();
+
+        // The requested start is rounded up to 1 MiB, the next one only to a sector
+        assert_eq!(spans(&table), [(1, 2048, 4095), (2, 34, 1057)]);
+
+        let error = table
+            .add(PartitionOptions::new().start(MIB).size(MIB))
+            .unwrap_err();
+        assert_eq!(kind(error), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn logical_partitions() {
+        let mut table = PartitionTable::new(TableKind::Mbr, DISK_SIZE, 512).unwrap();
+        let logical = || {
+            let mut options = PartitionOptions::new();
+            options.size(2 * MIB).logical(true);
+            options
+        };
+
+        assert!(table.add(&logical()).is_err());
+
+        table.add(PartitionOptions::new().size(2 * MIB)).unwrap();
+        table
+            .add(PartitionOptions::new().partition_type(PartitionType::Mbr(0x05)))
+            .unwrap();
+
+        for _ in 0..3 {
+            table.add(&logical()).unwrap();
+        }
+
+        // Each logical partition leaves room for its boot record and starts aligned
+        assert_eq!(
+            spans(&table),
+            [
+                (1, 2048, 6143),
+                (2, 6144, 32767),
+                (5, 8192, 12287),
+                (6, 14336, 18431),
+                (7, 20480, 24575),
+            ]
+        );
+
+        // Logical partitions are numbered in disk order, so later ones shift down and back
+        table.delete(5).unwrap();
+        assert_eq!(spans(&table)[2..], [(5, 14336, 18431), (6, 20480, 24575)]);
+        assert_eq!(table.add(&logical()).unwrap(), 5);
+        assert_eq!(
+            spans(&table)[2..],
+            [(5, 8192, 12287), (6, 14336, 18431), (7, 20480, 24575)]
+        );
+
+        // The first record is at the start of the extended partition, others right before
+        // their partition
+        let (read, disk) = round_trip(&table);
+        assert_eq!(spans(&read), spans(&table));
+
+        for record in [6144, 14335, 20479] {
+            let sector = &disk[record * 512..(record + 1) * 512];
+            assert_eq!(sector[510..], [0x55, 0xaa]);
+        }
+
+        // Deleting the extended partition takes its logical partitions along
+        table.delete(2).unwrap();
+        assert_eq!(spans(&table), [(1, 2048, 6143)]);
+    }
+
+    #[test]
+    fn invalid_options() {
+        let mut gpt = PartitionTable::new(TableKind::Gpt, DISK_SIZE, 512).unwrap();
+        assert!(gpt.add(PartitionOptions::new().logical(true)).is_err());
+        assert!(gpt
+            .add(PartitionOptions::new().partition_type(PartitionType::Mbr(0x83)))
+            .is_err());
+        assert!(gpt.add(PartitionOptions::new().size(DISK_SIZE)).is_err());
+
+        let mut mbr = PartitionTable::new(TableKind::Mbr, DISK_SIZE, 512).unwrap();
+        assert!(mbr.add(PartitionOptions::new().number(5)).is_err());
+        assert!(mbr.add(PartitionOptions::new().name("root")).is_err());
+        assert!(mbr.rename(1, "root").is_err());
+
+        let extended = PartitionType::Mbr(0x0f);
+        mbr.add(PartitionOptions::new().size(MIB).partition_type(extended))
+            .unwrap();
+        assert!(mbr
+            .add(PartitionOptions::new().partition_type(extended))
+            .is_err());
+    }
+}
@@ -0,0 +1,281 @@
+use super::{
+    le32, le64, mbr, read_at, write_at, Guid, Partition, PartitionTable, PartitionType, Source,
+    TableKind, Target, ALIGNMENT,
+};
+use std::io;
+
+pub const LEGACY_BIOS_BOOTABLE: u64 = 1 << 2;
+
+const SIGNATURE: &[u8] = b"EFI PART";
+
+const REVISION: u32 = 0x0001_0000;
+
+/// Size of the header fields defined by the specification.
+const HEADER_SIZE: usize = 92;
+
+/// Smallest entry size allowed by the specification, and the one written.
+pub const ENTRY_SIZE: usize = 128;
+
+/// Partition names are limited to 36 UTF-16 code units.
+pub const NAME_LENGTH: usize = 36;
+
+/// The largest entry array read, well above the 16 KiB every tool creates.
+const MAX_ENTRIES_SIZE: usize = 1 << 20;
+
+struct Header {
+    current_lba: u64,
+    backup_lba: u64,
+    first_usable_lba: u64,
+    last_usable_lba: u64,
+    disk_guid: Guid,
+    count: u32,
+    entry_size: usize,
+    entries_crc: u32,
+    entries: Vec<u8>,
+}
+
+impl Header {
+    /// Reads the header at `lba` along with its entries. Returns `None` if there is no
+    /// header there or if it fails any check.
+    fn read(source: &mut dyn Source, lba: u64, sector_size: u64) -> io::Result<Option<Self>> {
+        let Some(mut header) = read_at(source, lba * sector_size, sector_size as usize)? else {
+            return Ok(None);
+        };
+
+        if !header.starts_with(SIGNATURE) {
+            return Ok(None);
+        }
+
+        let size = le32(&header, 12) as usize;
+
+        if !(HEADER_SIZE..=header.len()).contains(&size) {
+            return Ok(None);
+        }
+
+        // The checksum covers the header with its own field zeroed
+        let crc = le32(&header, 16);
+        header[16..20].fill(0);
+
+        if crc32(&header[..size]) != crc || le64(&header, 24) != lba {
+            return Ok(None);
+        }
+
+        let entries_lba = le64(&header, 72);
+        let count = le32(&header, 80);
+        let entry_size = le32(&header, 84) as usize;
+        let entries_size = (count as usize).saturating_mul(entry_size);
+
+        if entry_size < ENTRY_SIZE || entry_size % 8 != 0 || entries_size > MAX_ENTRIES_SIZE {
+            return Ok(None);
+        }
+
+        let Some(entries) = read_at(source, entries_lba * sector_size, entries_size)? else {
+            return Ok(None);
+        };
+
+        let entries_crc = le32(&header, 88);
+
+        if crc32(&entries) != entries_crc {
+            return Ok(None);
+        }
+
+        Ok(Some(Header {
+            current_lba: lba,
+            backup_lba: le64(&header, 32),
+            first_usable_lba: le64(&header, 40),
+            last_usable_lba: le64(&header, 48),
+            disk_guid: guid(&header, 56),
+            count,
+            entry_size,
+            entries_crc,
+            entries,
+        }))
+    }
+
+    /// Returns `true` if `backup` describes the same partitions as this header.
+    fn matches(&self, backup: &Header) -> bool {
+        self.backup_lba == backup.current_lba
+            && backup.backup_lba == self.current_lba
+            && self.first_usable_lba == backup.first_usable_lba
+            && self.last_usable_lba == backup.last_usable_lba
+            && self.disk_guid == backup.disk_guid
+            && self.count == backup.count
+            && self.entry_size == backup.entry_size
+            && self.entries_crc == backup.entries_crc
+    }
+
+    fn partitions(&self, sector_size: u64) -> Vec<Partition> {
+        self.entries
+            .chunks_exact(self.entry_size)
+            .zip(1..)
+            .filter(|(entry, _)| !guid(entry, 0).is_nil())
+            .map(|(entry, number)| {
+                let name: Vec<u16> = entry[56..128]
+                    .chunks_exact(2)
+                    .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                    .take_while(|&c| c != 0)
+                    .collect();
+
+                Partition {
+                    number,
+                    start_lba: le64(entry, 32),
+                    end_lba: le64(entry, 40),
+                    sector_size,
+                    partition_type: PartitionType::Gpt(guid(entry, 0)),
+                    guid: Some(guid(entry, 16)),
+                    name: Some(String::from_utf16_lossy(&name)),
+                    attributes: le64(entry, 48),
+                    boot_record: None,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Reads a GPT with sectors of `sector_size` bytes on a disk of `sectors` sectors.
+///
+/// The backup header is looked for where the primary one says it is, or in the last sector
+/// when the primary is damaged. Returns `None` if neither header is valid.
+pub fn read(
+    source: &mut dyn Source,
+    sector_size: u64,
+    sectors: u64,
+) -> io::Result<Option<PartitionTable>> {
+    let primary = Header::read(source, 1, sector_size)?;
+    let backup_lba = primary
+        .as_ref()
+        .map_or(sectors.saturating_sub(1), |primary| primary.backup_lba);
+    let backup = Header::read(source, backup_lba, sector_size)?;
+
+    let backup_valid = match (&primary, &backup) {
+        (Some(primary), Some(backup)) => primary.matches(backup),
+        (_, backup) => backup.is_some(),
+    };
+
+    let Some(header) = primary.as_ref().or(backup.as_ref()) else {
+        return Ok(None);
+    };
+
+    Ok(Some(PartitionTable {
+        kind: TableKind::Gpt,
+        sector_size,
+        disk_sectors: sectors,
+        disk_guid: Some(header.disk_guid),
+        disk_signature: None,
+        first_usable_lba: header.first_usable_lba,
+        last_usable_lba: header.last_usable_lba,
+        primary_valid: primary.is_some(),
+        backup_valid,
+        entries: header.count,
+        alignment: ALIGNMENT,
+        partitions: header.partitions(sector_size),
+    }))
+}
+
+/// Writes the protective MBR, the primary header and entries at the start of the disk and
+/// their backups at the end.
+pub fn write(table: &PartitionTable, target: &mut dyn Target) -> io::Result<()> {
+    let sector_size = table.sector_size;
+    let last_lba = table.disk_sectors - 1;
+
+    let mut entries = vec![0; table.entries as usize * ENTRY_SIZE];
+
+    for partition in &table.partitions {
+        let offset = (partition.number as usize - 1) * ENTRY_SIZE;
+        let entry = &mut entries[offset..offset + ENTRY_SIZE];
+        let PartitionType::Gpt(partition_type) = partition.partition_type else {
+            continue;
+        };
+
+        entry[0..16].copy_from_slice(&partition_type.to_bytes());
+        entry[16..32].copy_from_slice(&partition.guid.unwrap_or_default().to_bytes());
+        entry[32..40].copy_from_slice(&partition.start_lba.to_le_bytes());
+        entry[40..48].copy_from_slice(&partition.end_lba.to_le_bytes());
+        entry[48..56].copy_from_slice(&partition.attributes.to_le_bytes());
+
+        let name = partition.name.as_deref().unwrap_or_default().encode_utf16();
+
+        for (unit, c) in entry[56..].chunks_exact_mut(2).zip(name) {
+            unit.copy_from_slice(&c.to_le_bytes());
+        }
+    }
+
+    // The entries fill whole sectors, so stale entries past the end get cleared
+    let entries_sectors = (entries.len() as u64).div_ceil(sector_size);
+    let entries_crc = crc32(&entries);
+    entries.resize((entries_sectors * sector_size) as usize, 0);
+
+    let backup_entries_lba = last_lba - entries_sectors;
+    let header = |lba: u64, backup_lba: u64, entries_lba: u64| {
+        let mut header = vec![0; sector_size as usize];
+
+        header[0..8].copy_from_slice(SIGNATURE);
+        header[8..12].copy_from_slice(&REVISION.to_le_bytes());
+        header[12..16].copy_from_slice(&(HEADER_SIZE as u32).to_le_bytes());
+        header[24..32].copy_from_slice(&lba.to_le_bytes());
+        header[32..40].copy_from_slice(&backup_lba.to_le_bytes());
+        header[40..48].copy_from_slice(&table.first_usable_lba.to_le_bytes());
+        header[48..56].copy_from_slice(&table.last_usable_lba.to_le_bytes());
+        header[56..72].copy_from_slice(&table.disk_guid.unwrap_or_default().to_bytes());
+        header[72..80].copy_from_slice(&entries_lba.to_le_bytes());
+        header[80..84].copy_from_slice(&table.entries.to_le_bytes());
+        header[84..88].copy_from_slice(&(ENTRY_SIZE as u32).to_le_bytes());
+        header[88..92].copy_from_slice(&entries_crc.to_le_bytes());
+
+        let crc = crc32(&header[..HEADER_SIZE]);
+        header[16..20].copy_from_slice(&crc.to_le_bytes());
+
+        header
+    };
+
+    mbr::write_protective(table, target)?;
+
+    write_at(target, 2 * sector_size, &entries)?;
+    write_at(target, sector_size, &header(1, last_lba, 2))?;
+
+    write_at(target, backup_entries_lba * sector_size, &entries)?;
+    write_at(
+        target,
+        last_lba * sector_size,
+        &header(last_lba, 1, backup_entries_lba),
+    )?;
+
+    Ok(())
+}
+
+fn guid(buf: &[u8], offset: usize) -> Guid {
+    Guid::from_bytes(buf[offset..offset + 16].try_into().unwrap())
+}
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+}
+
+/// CRC32 as used by GPT, the same as zlib and Ethernet.
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, &byte| {
+        CRC32_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
@@ -0,0 +1,159 @@
+use super::{mbr, PartitionTable};
+use crate::Result;
+use nix::errno::Errno;
+use nix::libc::{self, c_char, c_int, c_void};
+use std::fs::{self, File};
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::{io, mem, slice};
+
+const BLKPG_ADD_PARTITION: c_int = 1;
+const BLKPG_DEL_PARTITION: c_int = 2;
+const BLKPG_RESIZE_PARTITION: c_int = 3;
+
+/// Size of the sectors sysfs counts partitions in, regardless of the device.
+const SECTOR_SIZE: u64 = 512;
+
+#[repr(C)]
+struct BlkpgIoctlArg {
+    op: c_int,
+    flags: c_int,
+    datalen: c_int,
+    data: *mut c_void,
+}
+
+#[repr(C)]
+struct BlkpgPartition {
+    start: i64,
+    length: i64,
+    pno: c_int,
+    devname: [c_char; 64],
+    volname: [c_char; 64],
+}
+
+mod ioctl {
+    use super::BlkpgIoctlArg;
+
+    nix::ioctl_none_bad!(blkrrpart, 0x125f);
+    nix::ioctl_write_ptr_bad!(blkpg, 0x1269, BlkpgIoctlArg);
+}
+
+/// A partition as the kernel sees it: number, offset and length in bytes.
+type Span = (u32, u64, u64);
+
+/// Tells the kernel about the partitions of `table`, which was just written to `device`.
+pub fn reread(device: &File, table: &PartitionTable) -> Result<()> {
+    match unsafe { ioctl::blkrrpart(device.as_raw_fd()) } {
+        Ok(_) => Ok(()),
+        // Some partitions are in use
+        Err(Errno::EBUSY) => update(device, table),
+        // The kernel doesn't partition this device
+        Err(Errno::EINVAL) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Updates the partitions the kernel knows about one by one, leaving those that didn't
+/// change alone so they can stay in use.
+fn update(device: &File, table: &PartitionTable) -> Result<()> {
+    let sector_size = table.sector_size();
+    let wanted: Vec<Span> = table
+        .partitions()
+        .iter()
+        .map(|p| {
+            // The kernel only maps the start of extended partitions, where the first
+            // extended boot record is
+            let length = match mbr::extended(slice::from_ref(p)) {
+                Some(_) => p.size().min(sector_size.max(2 * SECTOR_SIZE)),
+                None => p.size(),
+            };
+
+            (p.number(), p.offset(), length)
+        })
+        .collect();
+
+    let current = current(device)?;
+    let find = |spans: &[Span], number: u32| spans.iter().find(|span| span.0 == number).copied();
+
+    // Make room first, so that grown and added partitions don't overlap the old ones
+    for &(number, offset, length) in &current {
+        match find(&wanted, number) {
+            Some((_, start, size)) if start == offset && size < length => {
+                blkpg(device, BLKPG_RESIZE_PARTITION, (number, start, size))?
+            }
+            Some((_, start, _)) if start == offset => {}
+            _ => blkpg(device, BLKPG_DEL_PARTITION, (number, 0, 0))?,
+        }
+    }
+
+    for &(number, start, size) in &wanted {
+        match find(&current, number) {
+            Some((_, offset, length)) if offset == start && length < size => {
+                blkpg(device, BLKPG_RESIZE_PARTITION, (number, start, size))?
+            }
+            Some((_, offset, _)) if offset == start => {}
+            _ => blkpg(device, BLKPG_ADD_PARTITION, (number, start, size))?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the partitions the kernel currently has for `device` from sysfs.
+fn current(device: &File) -> Result<Vec<Span>> {
+    let rdev = device.metadata()?.rdev();
+    let sys =
+        Path::new("/sys/dev/block").join(format!("{}:{}", libc::major(rdev), libc::minor(rdev)));
+
+    let mut spans = Vec::new();
+
+    for entry in fs::read_dir(sys)? {
+        let path = entry?.path();
+
+        if !path.join("partition").exists() {
+            continue;
+        }
+
+        spans.push((
+            number(&path, "partition")? as u32,
+            number(&path, "start")? * SECTOR_SIZE,
+            number(&path, "size")? * SECTOR_SIZE,
+        ));
+    }
+
+    Ok(spans)
+}
+
+fn number(sys: &Path, attribute: &str) -> Result<u64> {
+    let path = sys.join(attribute);
+
+    fs::read_to_string(&path)?.trim().parse().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("malformed {}", path.display()),
+        )
+        .into()
+    })
+}
+
+fn blkpg(device: &File, op: c_int, (number, start, length): Span) -> Result<()> {
+    let mut partition = BlkpgPartition {
+        start: start as i64,
+        length: length as i64,
+        pno: number as c_int,
+        devname: [0; 64],
+        volname: [0; 64],
+    };
+
+    let arg = BlkpgIoctlArg {
+        op,
+        flags: 0,
+        datalen: mem::size_of::<BlkpgPartition>() as c_int,
+        data: &mut partition as *mut BlkpgPartition as *mut c_void,
+    };
+
+    unsafe { ioctl::blkpg(device.as_raw_fd(), &arg)? };
+
+    Ok(())
+}
@@ -0,0 +1,382 @@
+use super::{
+    gpt, le32, read_at, write_at, Partition, PartitionTable, PartitionType, Source, TableKind,
+    Target, ALIGNMENT,
+};
+use std::io;
+
+/// The most extended boot records followed, in case the chain loops.
+const MAX_LOGICAL: u32 = 128;
+
+const PROTECTIVE: u8 = 0xee;
+
+/// Type used for the links between extended boot records.
+const EXTENDED: u8 = 0x05;
+
+const BOOTABLE: u8 = 0x80;
+
+/// Cylinders, heads and sectors per track of the geometry CHS addresses are computed
+/// with. Nothing reads them anymore, but some firmware expects them to be consistent.
+const CYLINDERS: u64 = 1024;
+const HEADS: u64 = 255;
+const SECTORS: u64 = 63;
+
+#[derive(Clone, Copy)]
+struct Entry {
+    bootable: bool,
+    kind: u8,
+    start: u32,
+    sectors: u32,
+}
+
+impl Entry {
+    fn is_used(&self) -> bool {
+        self.kind != 0 && self.sectors != 0
+    }
+
+    fn is_extended(&self) -> bool {
+        is_extended(self.kind)
+    }
+
+    /// Encodes the entry, with `base` the sector its start is relative to.
+    fn encode(&self, base: u64) -> [u8; 16] {
+        let start = base + self.start as u64;
+        let end = start + (self.sectors as u64).saturating_sub(1);
+
+        let mut entry = [0; 16];
+        entry[0] = if self.bootable { BOOTABLE } else { 0 };
+        entry[1..4].copy_from_slice(&chs(start));
+        entry[4] = self.kind;
+        entry[5..8].copy_from_slice(&chs(end));
+        entry[8..12].copy_from_slice(&self.start.to_le_bytes());
+        entry[12..16].copy_from_slice(&self.sectors.to_le_bytes());
+
+        entry
+    }
+
+    /// Turns the entry into a partition, with `base` the sector its start is relative to.
+    fn partition(&self, number: u32, base: u64, sector_size: u64) -> Partition {
+        let start_lba = base + self.start as u64;
+
+        Partition {
+            number,
+            start_lba,
+            end_lba: start_lba + self.sectors as u64 - 1,
+            sector_size,
+            partition_type: PartitionType::Mbr(self.kind),
+            guid: None,
+            name: None,
+            attributes: if self.bootable {
+                gpt::LEGACY_BIOS_BOOTABLE
+            } else {
+                0
+            },
+            boot_record: None,
+        }
+    }
+}
+
+/// Returns `true` if `kind` is the type of an extended partition.
+pub fn is_extended(kind: u8) -> bool {
+    matches!(kind, 0x05 | 0x0f | 0x85)
+}
+
+/// Returns the extended partition among `partitions`, if any.
+pub fn extended(partitions: &[Partition]) -> Option<&Partition> {
+    partitions.iter().find(|p| {
+        p.number <= 4 && matches!(p.partition_type, PartitionType::Mbr(kind) if is_extended(kind))
+    })
+}
+
+/// Returns `true` if `partition` is a logical partition of an MBR partition table.
+pub fn is_logical(partition: &Partition) -> bool {
+    partition.number > 4 && matches!(partition.partition_type, PartitionType::Mbr(_))
+}
+
+/// A master or extended boot record.
+pub struct BootRecord {
+    signature: u32,
+    entries: [Entry; 4],
+}
+
+impl BootRecord {
+    /// Reads the master boot record of a disk of `disk_size` bytes. Returns `None` if
+    /// there is none.
+    ///
+    /// File system boot sectors have the same signature, and those of unpartitioned
+    /// vfat, exFAT or NTFS volumes are not mistaken for one, nor are records whose
+    /// partitions overlap or run past the end of the disk.
+    pub fn read(source: &mut dyn Source, disk_size: u64) -> io::Result<Option<Self>> {
+        let Some(sector) = read_at(source, 0, 512)? else {
+            return Ok(None);
+        };
+
+        let boot_sector = sector[3..11] == *b"NTFS    "
+            || sector[3..11] == *b"EXFAT   "
+            || sector[0x36..0x39] == *b"FAT"
+            || sector[0x52..0x57] == *b"FAT32";
+
+        let Some(record) = Self::parse(&sector).filter(|_| !boot_sector) else {
+            return Ok(None);
+        };
+
+        // The GPT is authoritative on protective and hybrid records, so their 0xee
+        // partition is not checked. Sectors are assumed to be of 512 bytes, which is
+        // the smallest they can be.
+        let ranges: Vec<_> = record
+            .entries
+            .iter()
+            .filter(|e| e.is_used() && e.kind != PROTECTIVE)
+            .map(|e| (e.start as u64, e.start as u64 + e.sectors as u64))
+            .collect();
+
+        let past_end = ranges.iter().any(|&(_, end)| end * 512 > disk_size);
+        let overlap = ranges.iter().enumerate().any(|(i, &(start, end))| {
+            ranges[i + 1..]
+                .iter()
+                .any(|&(other_start, other_end)| start < other_end && other_start < end)
+        });
+
+        Ok((!past_end && !overlap).then_some(record))
+    }
+
+    /// Reads the extended boot record at `offset`. Returns `None` if there is none.
+    fn read_extended(source: &mut dyn Source, offset: u64) -> io::Result<Option<Self>> {
+        Ok(read_at(source, offset, 512)?.and_then(|sector| Self::parse(&sector)))
+    }
+
+    fn parse(sector: &[u8]) -> Option<Self> {
+        if sector[510..512] != [0x55, 0xaa] {
+            return None;
+        }
+
+        let table = &sector[446..510];
+
+        // Anything other than 0x00 or 0x80 in a boot indicator means there is no
+        // partition table.
+        if table.chunks_exact(16).any(|e| e[0] & 0x7f != 0) {
+            return None;
+        }
+
+        let entry = |i: usize| {
+            let e = &table[i * 16..(i + 1) * 16];
+
+            Entry {
+                bootable: e[0] == BOOTABLE,
+                kind: e[4],
+                start: le32(e, 8),
+                sectors: le32(e, 12),
+            }
+        };
+
+        Some(BootRecord {
+            signature: le32(sector, 440),
+            entries: [entry(0), entry(1), entry(2), entry(3)],
+        })
+    }
+
+    /// Returns `true` if the record protects a GPT disk.
+    pub fn is_protective(&self) -> bool {
+        self.entries.iter().any(|e| e.kind == PROTECTIVE)
+    }
+}
+
+/// Reads the partitions of `mbr` and of the extended partition it may have.
+pub fn read(
+    source: &mut dyn Source,
+    mbr: &BootRecord,
+    sector_size: u64,
+    disk_size: u64,
+) -> io::Result<PartitionTable> {
+    let mut partitions = Vec::new();
+    let mut extended = None;
+
+    for (entry, number) in mbr.entries.iter().zip(1..) {
+        if entry.is_used() {
+            partitions.push(entry.partition(number, 0, sector_size));
+
+            if entry.is_extended() && extended.is_none() {
+                extended = Some(entry.start as u64);
+            }
+        }
+    }
+
+    // Logical partitions are a linked list of extended boot records, each one holding
+    // a partition relative to itself and a link relative to the extended partition.
+    if let Some(extended) = extended {
+        let mut ebr = extended;
+        let mut number = 5;
+
+        for _ in 0..MAX_LOGICAL {
+            let Some(record) = BootRecord::read_extended(source, ebr * sector_size)? else {
+                break;
+            };
+
+            let [logical, link, ..] = record.entries;
+
+            if logical.is_used() && !logical.is_extended() {
+                let mut partition = logical.partition(number, ebr, sector_size);
+                partition.boot_record = Some(ebr);
+                partitions.push(partition);
+                number += 1;
+            }
+
+            if !link.is_extended() || link.start == 0 {
+                break;
+            }
+
+            ebr = extended + link.start as u64;
+        }
+    }
+
+    let sectors = disk_size / sector_size;
+
+    Ok(PartitionTable {
+        kind: TableKind::Mbr,
+        sector_size,
+        disk_sectors: sectors,
+        disk_guid: None,
+        disk_signature: Some(mbr.signature),
+        first_usable_lba: 1,
+        last_usable_lba: sectors.min(u32::MAX as u64).saturating_sub(1),
+        primary_valid: true,
+        backup_valid: true,
+        entries: 0,
+        alignment: ALIGNMENT,
+        partitions,
+    })
+}
+
+/// Writes a protective MBR covering the whole disk, as GPT requires.
+pub fn write_protective(table: &PartitionTable, target: &mut dyn Target) -> io::Result<()> {
+    let protective = Entry {
+        bootable: false,
+        kind: PROTECTIVE,
+        start: 1,
+        sectors: (table.disk_sectors - 1).min(u32::MAX as u64) as u32,
+    };
+
+    write_record(target, 0, 0, None, &[(protective, 0)])
+}
+
+/// Writes the boot record and the chain of extended boot records of an MBR partition
+/// table.
+pub fn write(table: &PartitionTable, target: &mut dyn Target) -> io::Result<()> {
+    let sector_size = table.sector_size;
+    let mut entries = [Entry {
+        bootable: false,
+        kind: 0,
+        start: 0,
+        sectors: 0,
+    }; 4];
+
+    for partition in table.partitions.iter().filter(|p| p.number <= 4) {
+        entries[partition.number as usize - 1] = entry(partition, 0);
+    }
+
+    let entries = entries.map(|entry| (entry, 0));
+    write_record(target, 0, sector_size, table.disk_signature, &entries)?;
+
+    let Some(extended) = extended(&table.partitions) else {
+        return Ok(());
+    };
+
+    let logical: Vec<&Partition> = table.partitions.iter().filter(|p| p.number > 4).collect();
+
+    // The first record is at the start of the extended partition. Others keep their place
+    // when it is still free, or go right before their partition.
+    let mut records = Vec::with_capacity(logical.len());
+    let mut previous_end = extended.start_lba;
+
+    for (i, partition) in logical.iter().enumerate() {
+        let record = match partition.boot_record {
+            _ if i == 0 => extended.start_lba,
+            Some(record) if record > previous_end && record < partition.start_lba => record,
+            _ => partition.start_lba - 1,
+        };
+
+        records.push(record);
+        previous_end = partition.end_lba;
+    }
+
+    if logical.is_empty() {
+        // An empty record clears the chain of a previous table
+        return write_record(target, extended.start_lba, sector_size, None, &[]);
+    }
+
+    for (i, (partition, &record)) in logical.iter().zip(&records).enumerate() {
+        let mut entries = vec![(entry(partition, record), record)];
+
+        if let (Some(next), Some(&next_record)) = (logical.get(i + 1), records.get(i + 1)) {
+            let link = Entry {
+                bootable: false,
+                kind: EXTENDED,
+                start: (next_record - extended.start_lba) as u32,
+                sectors: (next.end_lba + 1 - next_record) as u32,
+            };
+
+            entries.push((link, extended.start_lba));
+        }
+
+        write_record(target, record, sector_size, None, &entries)?;
+    }
+
+    Ok(())
+}
+
+/// Builds the entry of `partition`, with `base` the sector its start is relative to.
+fn entry(partition: &Partition, base: u64) -> Entry {
+    Entry {
+        bootable: partition.is_bootable(),
+        kind: match partition.partition_type {
+            PartitionType::Mbr(kind) => kind,
+            PartitionType::Gpt(_) => 0,
+        },
+        start: (partition.start_lba - base) as u32,
+        sectors: partition.sectors() as u32,
+    }
+}
+
+/// Writes a boot record at `lba`, along with the sector each entry is relative to. The
+/// boot code and disk signature already there are kept unless `signature` is set.
+fn write_record(
+    target: &mut dyn Target,
+    lba: u64,
+    sector_size: u64,
+    signature: Option<u32>,
+    entries: &[(Entry, u64)],
+) -> io::Result<()> {
+    let offset = lba * sector_size;
+    let mut sector = read_at(target, offset, 512)?.unwrap_or_else(|| vec![0; 512]);
+
+    if let Some(signature) = signature {
+        sector[440..444].copy_from_slice(&signature.to_le_bytes());
+        sector[444..446].fill(0);
+    }
+
+    sector[446..510].fill(0);
+
+    for (i, (entry, base)) in entries.iter().enumerate() {
+        sector[446 + i * 16..446 + (i + 1) * 16].copy_from_slice(&entry.encode(*base));
+    }
+
+    sector[510..512].copy_from_slice(&[0x55, 0xaa]);
+
+    write_at(target, offset, &sector)
+}
+
+/// Returns the CHS address of `lba`, or the largest one if it is out of reach.
+fn chs(lba: u64) -> [u8; 3] {
+    if lba >= CYLINDERS * HEADS * SECTORS {
+        return [0xfe, 0xff, 0xff];
+    }
+
+    let cylinder = lba / (HEADS * SECTORS);
+    let head = lba / SECTORS % HEADS;
+    let sector = lba % SECTORS + 1;
+
+    [
+        head as u8,
+        sector as u8 | ((cylinder >> 2) & 0xc0) as u8,
+        cylinder as u8,
+    ]
+}
@@ -0,0 +1,801 @@
+//! Parsing of GPT and MBR partition tables, public as `device::partition_table`.
+
+mod edit;
+mod gpt;
+#[cfg(all(feature = "device", any(target_os = "linux", target_os = "android")))]
+mod linux;
+mod mbr;
+
+pub use edit::PartitionOptions;
+
+use crate::{Error, Result};
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Sector sizes tried when looking for a GPT header, most common first.
+const SECTOR_SIZES: &[u64] = &[512, 4096];
+
+/// Sector size assumed for MBR partition tables, which don't record it.
+const MBR_SECTOR_SIZE: u64 = 512;
+
+/// Partitions start at multiples of 1 MiB by default, as every partitioning tool does.
+const ALIGNMENT: u64 = 1 << 20;
+
+/// Number of entries of a new GPT, the minimum allowed by the specification.
+const GPT_ENTRIES: u32 = 128;
+
+trait Source: Read + Seek {}
+
+impl<T: Read + Seek> Source for T {}
+
+trait Target: Source + Write {}
+
+impl<T: Read + Write + Seek> Target for T {}
+
+/// A GUID, used by GPT to identify disks, partitions and partition types.
+///
+/// GUIDs are stored in the mixed-endian layout they have on disk and formatted in the
+/// usual `c12a7328-f81f-11d2-ba4b-00a0c93ec93b` form.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Guid([u8; 16]);
+
+impl Guid {
+    /// The all-zero GUID, which marks unused GPT entries.
+    pub const NIL: Guid = Guid([0; 16]);
+
+    /// Partition type of EFI system partitions.
+    pub const EFI_SYSTEM: Guid = Guid::from_fields(
+        0xc12a7328,
+        0xf81f,
+        0x11d2,
+        [0xba, 0x4b, 0x00, 0xa0, 0xc9, 0x3e, 0xc9, 0x3b],
+    );
+
+    /// Partition type of BIOS boot partitions, used by GRUB on GPT disks booted by legacy
+    /// BIOS.
+    pub const BIOS_BOOT: Guid = Guid::from_fields(
+        0x21686148,
+        0x6449,
+        0x6e6f,
+        [0x74, 0x4e, 0x65, 0x65, 0x64, 0x45, 0x46, 0x49],
+    );
+
+    /// Partition type of Linux file systems.
+    pub const LINUX_FILESYSTEM: Guid = Guid::from_fields(
+        0x0fc63daf,
+        0x8483,
+        0x4772,
+        [0x8e, 0x79, 0x3d, 0x69, 0xd8, 0x47, 0x7d, 0xe4],
+    );
+
+    /// Partition type of Linux swap.
+    pub const LINUX_SWAP: Guid = Guid::from_fields(
+        0x0657fd6d,
+        0xa4ab,
+        0x43c4,
+        [0x84, 0xe5, 0x09, 0x33, 0xc8, 0x4b, 0x4f, 0x4f],
+    );
+
+    /// Partition type of LVM physical volumes.
+    pub const LINUX_LVM: Guid = Guid::from_fields(
+        0xe6d6d379,
+        0xf507,
+        0x44c2,
+        [0xa2, 0x3c, 0x23, 0x8f, 0x2a, 0x3d, 0xf9, 0x28],
+    );
+
+    /// Partition type of Linux software RAID members.
+    pub const LINUX_RAID: Guid = Guid::from_fields(
+        0xa19d880f,
+        0x05fc,
+        0x4d3b,
+        [0xa0, 0x06, 0x74, 0x3f, 0x0f, 0x84, 0x91, 0x1e],
+    );
+
+    /// Partition type of Windows data partitions, also used for FAT and exFAT.
+    pub const MICROSOFT_BASIC_DATA: Guid = Guid::from_fields(
+        0xebd0a0a2,
+        0xb9e5,
+        0x4433,
+        [0x87, 0xc0, 0x68, 0xb6, 0xb7, 0x26, 0x99, 0xc7],
+    );
+
+    /// Creates a GUID from the fields of its text form, e.g. `0x0fc63daf`, `0x8483`,
+    /// `0x4772` and `[0x8e, 0x79, ...]` for `0fc63daf-8483-4772-8e79-...`.
+    const fn from_fields(a: u32, b: u16, c: u16, d: [u8; 8]) -> Self {
+        let [a0, a1, a2, a3] = a.to_le_bytes();
+        let [b0, b1] = b.to_le_bytes();
+        let [c0, c1] = c.to_le_bytes();
+
+        Guid([
+            a0, a1, a2, a3, b0, b1, c0, c1, d[0], d[1], d[2], d[3], d[4], d[5], d[6], d[7],
+        ])
+    }
+
+    /// Creates a random (version 4) GUID.
+    pub fn random() -> Self {
+        let mut bytes = [0; 16];
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+
+        // Every RandomState gets different keys, derived from a random per-thread seed
+        for chunk in bytes.chunks_exact_mut(8) {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u128(time);
+            chunk.copy_from_slice(&hasher.finish().to_le_bytes());
+        }
+
+        // Version and variant bits, where the mixed-endian layout puts them
+        bytes[7] = (bytes[7] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+        Guid(bytes)
+    }
+
+    /// Creates a GUID from its on-disk bytes.
+    pub const fn from_bytes(bytes: [u8; 16]) -> Self {
+        Guid(bytes)
+    }
+
+    /// Returns the on-disk bytes of the GUID.
+    pub const fn to_bytes(self) -> [u8; 16] {
+        self.0
+    }
+
+    /// Returns `true` if this is the all-zero GUID.
+    pub fn is_nil(&self) -> bool {
+        *self == Self::NIL
+    }
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let b = &self.0;
+
+        // The first three fields are little-endian, the last two are plain bytes
+        write!(
+            f,
+            "{:08x}-{:04x}-{:04x}-",
+            u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            u16::from_le_bytes([b[4], b[5]]),
+            u16::from_le_bytes([b[6], b[7]]),
+        )?;
+
+        for (i, byte) in b[8..].iter().enumerate() {
+            if i == 2 {
+                f.write_str("-")?;
+            }
+
+            write!(f, "{byte:02x}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Guid({self})")
+    }
+}
+
+impl FromStr for Guid {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let malformed = || -> crate::Error {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("malformed GUID: {s}")).into()
+        };
+
+        let groups: Vec<&str> = s.split('-').collect();
+
+        if groups.iter().map(|group| group.len()).ne([8, 4, 4, 4, 12]) {
+            return Err(malformed());
+        }
+
+        let hex = groups.concat();
+        let mut bytes = [0; 16];
+
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = hex
+                .get(i * 2..i * 2 + 2)
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .ok_or_else(malformed)?;
+        }
+
+        bytes[0..4].reverse();
+        bytes[4..6].reverse();
+        bytes[6..8].reverse();
+
+        Ok(Guid(bytes))
+    }
+}
+
+/// The kind of a [`PartitionTable`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TableKind {
+    /// GUID Partition Table.
+    Gpt,
+    /// Master Boot Record, also known as DOS partition table.
+    Mbr,
+}
+
+/// The type of a [`Partition`], telling what it holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PartitionType {
+    /// Type GUID of a GPT partition, such as `0fc63daf-8483-4772-8e79-3d69d8477de4` for
+    /// Linux file systems.
+    Gpt(Guid),
+    /// System ID of an MBR partition, such as `0x83` for Linux file systems.
+    Mbr(u8),
+}
+
+/// A partition of a [`PartitionTable`].
+///
+/// Positions are counted in logical blocks (LBAs) of the sector size of the table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Partition {
+    number: u32,
+    start_lba: u64,
+    end_lba: u64,
+    sector_size: u64,
+    partition_type: PartitionType,
+    guid: Option<Guid>,
+    name: Option<String>,
+    attributes: u64,
+    /// Sector of the extended boot record of a logical MBR partition, when read from disk.
+    boot_record: Option<u64>,
+}
+
+impl Partition {
+    /// Number the kernel gives to the partition, starting at 1.
+    ///
+    /// GPT partitions are numbered after their entry in the table, so numbers can have
+    /// gaps. Logical partitions of an MBR partition table start at 5.
+    pub fn number(&self) -> u32 {
+        self.number
+    }
+
+    /// First block of the partition.
+    pub fn start_lba(&self) -> u64 {
+        self.start_lba
+    }
+
+    /// Last block of the partition, which is part of it.
+    pub fn end_lba(&self) -> u64 {
+        self.end_lba
+    }
+
+    /// Number of blocks in the partition.
+    pub fn sectors(&self) -> u64 {
+        (self.end_lba + 1).saturating_sub(self.start_lba)
+    }
+
+    /// Offset of the partition from the start of the disk, in bytes.
+    pub fn offset(&self) -> u64 {
+        self.start_lba * self.sector_size
+    }
+
+    /// Size of the partition in bytes.
+    pub fn size(&self) -> u64 {
+        self.sectors() * self.sector_size
+    }
+
+    /// What the partition holds.
+    pub fn partition_type(&self) -> PartitionType {
+        self.partition_type
+    }
+
+    /// Unique GUID of the partition, known as `PARTUUID`. Only GPT partitions have one.
+    pub fn guid(&self) -> Option<Guid> {
+        self.guid
+    }
+
+    /// Name of the partition, known as `PARTLABEL`. Only GPT partitions have one.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// GPT attribute flags of the partition.
+    ///
+    /// Bit 0 marks partitions required by the platform, bit 1 those firmware should not
+    /// read, and bit 2 those legacy BIOS can boot from. Bits 48 to 63 depend on the type of
+    /// the partition. For MBR partitions, only bit 2 is set, from the boot indicator.
+    pub fn attributes(&self) -> u64 {
+        self.attributes
+    }
+
+    /// Whether the partition is marked as bootable by legacy BIOS.
+    pub fn is_bootable(&self) -> bool {
+        self.attributes & gpt::LEGACY_BIOS_BOOTABLE != 0
+    }
+}
+
+/// A GPT or MBR partition table.
+///
+/// GPT headers and their partition entries are checked against their CRC32. If the
+/// primary header is damaged, partitions are read from the backup at the end of the disk
+/// instead, and the backup is compared with the primary to detect a stale or missing copy.
+///
+/// MBR extended partitions are followed, so logical partitions are included. The extended
+/// partition itself is reported as well, as the kernel does.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartitionTable {
+    kind: TableKind,
+    sector_size: u64,
+    disk_sectors: u64,
+    disk_guid: Option<Guid>,
+    disk_signature: Option<u32>,
+    first_usable_lba: u64,
+    last_usable_lba: u64,
+    primary_valid: bool,
+    backup_valid: bool,
+    entries: u32,
+    alignment: u64,
+    partitions: Vec<Partition>,
+}
+
+impl PartitionTable {
+    /// Creates an empty partition table for a disk of `disk_size` bytes with sectors of
+    /// `sector_size` bytes. Nothing is written until [`PartitionTable::write`] is called.
+    ///
+    /// A GPT gets a random disk GUID and room for 128 partitions. An MBR gets a random
+    /// disk signature.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`io::ErrorKind::InvalidInput`] error if `sector_size` is not a power of
+    /// two of at least 512 bytes, or if the disk is too small to hold the table.
+    pub fn new(kind: TableKind, disk_size: u64, sector_size: u64) -> Result<Self> {
+        if sector_size < 512 || !sector_size.is_power_of_two() {
+            return Err(invalid(format!("invalid sector size {sector_size}")));
+        }
+
+        let sectors = disk_size / sector_size;
+        let (entries, first_usable_lba, last_usable_lba) = match kind {
+            TableKind::Gpt => {
+                let entries_sectors =
+                    (GPT_ENTRIES as u64 * gpt::ENTRY_SIZE as u64).div_ceil(sector_size);
+
+                (
+                    GPT_ENTRIES,
+                    2 + entries_sectors,
+                    sectors.saturating_sub(2 + entries_sectors),
+                )
+            }
+            TableKind::Mbr => (0, 1, sectors.min(u32::MAX as u64).saturating_sub(1)),
+        };
+
+        if first_usable_lba > last_usable_lba {
+            return Err(invalid(format!(
+                "a disk of {disk_size} bytes is too small for a partition table"
+            )));
+        }
+
+        let random = Guid::random();
+
+        Ok(PartitionTable {
+            kind,
+            sector_size,
+            disk_sectors: sectors,
+            disk_guid: (kind == TableKind::Gpt).then_some(random),
+            disk_signature: (kind == TableKind::Mbr)
+                .then(|| u32::from_le_bytes(random.0[..4].try_into().unwrap())),
+            first_usable_lba,
+            last_usable_lba,
+            primary_valid: true,
+            backup_valid: true,
+            entries,
+            alignment: ALIGNMENT,
+            partitions: Vec::new(),
+        })
+    }
+
+    /// Reads the partition table in `source`. Returns `None` if there is none.
+    ///
+    /// The sector size of GPT disks is found by looking for the header at 512 and 4096
+    /// bytes. MBR partition tables are assumed to use 512 byte sectors; use
+    /// [`PartitionTable::read_with_sector_size`] for disks with larger sectors.
+    ///
+    /// # Errors
+    ///
+    /// Besides I/O errors, an [`io::ErrorKind::InvalidData`] error is returned if the disk
+    /// has a protective MBR but neither GPT header is valid.
+    pub fn read<R: Read + Seek>(source: &mut R) -> Result<Option<Self>> {
+        Ok(read(source, None)?)
+    }
+
+    /// Reads the partition table in `source`, which has sectors of `sector_size` bytes.
+    ///
+    /// The sector size of a block device is its logical sector size, as given by
+    /// [`crate::device::Device::logical_sector_size`].
+    ///
+    /// # Errors
+    ///
+    /// Same as [`PartitionTable::read`].
+    pub fn read_with_sector_size<R: Read + Seek>(
+        source: &mut R,
+        sector_size: u64,
+    ) -> Result<Option<Self>> {
+        Ok(read(source, Some(sector_size))?)
+    }
+
+    /// Whether this is a GPT or an MBR partition table.
+    pub fn kind(&self) -> TableKind {
+        self.kind
+    }
+
+    /// Size of the blocks partitions are counted in, in bytes.
+    pub fn sector_size(&self) -> u64 {
+        self.sector_size
+    }
+
+    /// Size of the disk in bytes, rounded down to a whole number of sectors.
+    pub fn disk_size(&self) -> u64 {
+        self.disk_sectors * self.sector_size
+    }
+
+    /// Boundary in bytes partitions added or resized are aligned to.
+    pub fn alignment(&self) -> u64 {
+        self.alignment
+    }
+
+    /// Sets the boundary in bytes partitions added or resized are aligned to. Defaults to
+    /// 1 MiB, which suits every disk. Use the sector size to disable alignment.
+    pub fn set_alignment(&mut self, alignment: u64) -> &mut Self {
+        self.alignment = alignment;
+        self
+    }
+
+    /// GUID of the disk, for GPT partition tables.
+    pub fn disk_guid(&self) -> Option<Guid> {
+        self.disk_guid
+    }
+
+    /// Disk signature of MBR partition tables, which the kernel uses to build the
+    /// `PARTUUID` of their partitions.
+    pub fn disk_signature(&self) -> Option<u32> {
+        self.disk_signature
+    }
+
+    /// First block that can be used by partitions.
+    pub fn first_usable_lba(&self) -> u64 {
+        self.first_usable_lba
+    }
+
+    /// Last block that can be used by partitions.
+    pub fn last_usable_lba(&self) -> u64 {
+        self.last_usable_lba
+    }
+
+    /// Whether the primary GPT header and its entries pass their CRC checks.
+    ///
+    /// Always `true` for MBR partition tables, which have no checksums.
+    pub fn is_primary_valid(&self) -> bool {
+        self.primary_valid
+    }
+
+    /// Whether the backup GPT header and its entries pass their CRC checks and match the
+    /// primary ones.
+    ///
+    /// Always `true` for MBR partition tables, which have no backup.
+    pub fn is_backup_valid(&self) -> bool {
+        self.backup_valid
+    }
+
+    /// Partitions of the table, sorted by number.
+    pub fn partitions(&self) -> &[Partition] {
+        &self.partitions
+    }
+
+    /// Returns the partition with the given number, if any.
+    pub fn partition(&self, number: u32) -> Option<&Partition> {
+        self.partitions.iter().find(|p| p.number == number)
+    }
+
+    /// Writes the partition table to the disk or image file at `path`.
+    ///
+    /// GPT writes the protective MBR, both headers and both copies of the entries, so the
+    /// backup is always in sync. Hybrid MBRs are replaced by a protective one. MBR writes the
+    /// boot record and the extended boot records of logical partitions. Boot code is kept
+    /// in both cases.
+    ///
+    /// # Platform-specific behaviour
+    ///
+    /// On Linux and Android, when `path` is a block device, the kernel is asked to re-read
+    /// the partition table with `BLKRRPART`. If some partitions are in use, the changed ones
+    /// are updated one by one with `BLKPG` instead, as `sfdisk` does. Devices the kernel
+    /// doesn't partition, such as loop devices without partition scanning, are left as is.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error::Busy`] error if a partition that changed is in use, in which case
+    /// the table is written but the kernel still has the previous partitions.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+
+        self.write_file(path)
+            .map_err(|e| e.context("write partition table to", [path]))
+    }
+
+    fn write_file(&self, path: &Path) -> Result<()> {
+        let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+
+        self.write_to(&mut file)?;
+        file.sync_all()?;
+
+        #[cfg(all(feature = "device", any(target_os = "linux", target_os = "android")))]
+        {
+            use std::os::unix::fs::FileTypeExt;
+
+            if file.metadata()?.file_type().is_block_device() {
+                linux::reread(&file, self)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the partition table to `target`, which can be anything holding a disk image.
+    ///
+    /// Unlike [`PartitionTable::write`], the kernel is never told about the change.
+    ///
+    /// # Errors
+    ///
+    /// Besides I/O errors, an [`io::ErrorKind::InvalidInput`] error is returned if
+    /// partitions overlap or don't fit in the disk, which can only happen with tables
+    /// read from a damaged disk.
+    pub fn write_to<T: Read + Write + Seek>(&self, target: &mut T) -> Result<()> {
+        self.check(&self.partitions)?;
+
+        match self.kind {
+            TableKind::Gpt => gpt::write(self, target)?,
+            TableKind::Mbr => mbr::write(self, target)?,
+        }
+
+        target.flush()?;
+
+        Ok(())
+    }
+}
+
+fn read(source: &mut dyn Source, sector_size: Option<u64>) -> io::Result<Option<PartitionTable>> {
+    let disk_size = source.seek(SeekFrom::End(0))?;
+
+    let Some(mbr) = mbr::BootRecord::read(source, disk_size)? else {
+        return Ok(None);
+    };
+
+    // A protective MBR covers the disk with a single 0xee partition; hybrid MBRs add
+    // others next to it, but the GPT is authoritative in both cases.
+    if !mbr.is_protective() {
+        let sector_size = sector_size.unwrap_or(MBR_SECTOR_SIZE);
+        return mbr::read(source, &mbr, sector_size, disk_size).map(Some);
+    }
+
+    let sector_sizes = match &sector_size {
+        Some(sector_size) => std::slice::from_ref(sector_size),
+        None => SECTOR_SIZES,
+    };
+
+    for &sector_size in sector_sizes {
+        if let Some(table) = gpt::read(source, sector_size, disk_size / sector_size)? {
+            return Ok(Some(table));
+        }
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "protective MBR found but no valid GPT header",
+    ))
+}
+
+fn invalid(message: String) -> Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message).into()
+}
+
+/// Reads `len` bytes at `offset`. Returns `None` if the source is too small.
+fn read_at(source: &mut dyn Source, offset: u64, len: usize) -> io::Result<Option<Vec<u8>>> {
+    let mut buf = vec![0; len];
+    source.seek(SeekFrom::Start(offset))?;
+
+    match source.read_exact(&mut buf) {
+        Ok(()) => Ok(Some(buf)),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn write_at(target: &mut dyn Target, offset: u64, buf: &[u8]) -> io::Result<()> {
+    target.seek(SeekFrom::Start(offset))?;
+    target.write_all(buf)
+}
+
+fn le32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+fn le64(buf: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const DISK_SIZE: u64 = 8 << 20;
+
+    fn disk() -> Cursor<Vec<u8>> {
+        Cursor::new(vec![0; DISK_SIZE as usize])
+    }
+
+    fn gpt() -> (PartitionTable, Cursor<Vec<u8>>) {
+        let mut table = PartitionTable::new(TableKind::Gpt, DISK_SIZE, 512).unwrap();
+        table
+            .add(
+                PartitionOptions::new()
+                    .size(1 << 20)
+                    .partition_type(PartitionType::Gpt(Guid::EFI_SYSTEM))
+                    .name("esp"),
+            )
+            .unwrap();
+        table.add(PartitionOptions::new().name("root")).unwrap();
+
+        let mut disk = disk();
+        table.write_to(&mut disk).unwrap();
+
+        (table, disk)
+    }
+
+    /// Writes a boot record at `lba` with `(type, start, sectors)` entries.
+    fn boot_record(disk: &mut [u8], lba: usize, entries: &[(u8, u32, u32)]) {
+        let sector = &mut disk[lba * 512..(lba + 1) * 512];
+
+        for (entry, &(kind, start, sectors)) in sector[446..].chunks_exact_mut(16).zip(entries) {
+            entry[4] = kind;
+            entry[8..12].copy_from_slice(&start.to_le_bytes());
+            entry[12..16].copy_from_slice(&sectors.to_le_bytes());
+        }
+
+        sector[510..512].copy_from_slice(&[0x55, 0xaa]);
+    }
+
+    #[test]
+    fn gpt_round_trip() {
+        let (table, mut disk) = gpt();
+        let read = PartitionTable::read(&mut disk).unwrap().unwrap();
+
+        assert_eq!(read, table);
+        assert!(read.is_primary_valid() && read.is_backup_valid());
+
+        let esp = read.partition(1).unwrap();
+        assert_eq!(esp.partition_type(), PartitionType::Gpt(Guid::EFI_SYSTEM));
+        assert_eq!(esp.name(), Some("esp"));
+        assert_eq!((esp.offset(), esp.size()), (1 << 20, 1 << 20));
+
+        let root = read.partition(2).unwrap();
+        assert_eq!(root.name(), Some("root"));
+        assert_eq!(root.end_lba(), read.last_usable_lba());
+    }
+
+    #[test]
+    fn gpt_backup_fallback() {
+        let (table, disk) = gpt();
+
+        // Damaging the primary header, or the primary entries, fails their checksum
+        for offset in [512 + 56, 2 * 512 + 56] {
+            let mut damaged = disk.clone().into_inner();
+            damaged[offset] ^= 0xff;
+
+            let read = PartitionTable::read(&mut Cursor::new(damaged))
+                .unwrap()
+                .unwrap();
+
+            assert!(!read.is_primary_valid() && read.is_backup_valid());
+            assert_eq!(read.disk_guid(), table.disk_guid());
+            assert_eq!(read.partitions(), table.partitions());
+        }
+
+        let mut damaged = disk.into_inner();
+        damaged[DISK_SIZE as usize - 512 + 56] ^= 0xff;
+
+        let read = PartitionTable::read(&mut Cursor::new(damaged))
+            .unwrap()
+            .unwrap();
+
+        assert!(read.is_primary_valid() && !read.is_backup_valid());
+        assert_eq!(read.partitions(), table.partitions());
+    }
+
+    #[test]
+    fn gpt_without_headers() {
+        let (_, disk) = gpt();
+        let mut damaged = disk.into_inner();
+        damaged[512] = 0;
+        damaged[DISK_SIZE as usize - 512] = 0;
+
+        let error = PartitionTable::read(&mut Cursor::new(damaged)).unwrap_err();
+
+        assert_eq!(io::Error::from(error).kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn mbr_extended_chain() {
+        let mut disk = disk().into_inner();
+
+        // A primary partition, then an extended one holding two logical partitions, with
+        // the second record linked relative to the start of the extended partition
+        boot_record(&mut disk, 0, &[(0x83, 2048, 2048), (0x0f, 4096, 8192)]);
+        boot_record(&mut disk, 4096, &[(0x83, 2048, 2048), (0x05, 4096, 4096)]);
+        boot_record(&mut disk, 8192, &[(0x82, 2048, 2048)]);
+
+        let table = PartitionTable::read(&mut Cursor::new(disk))
+            .unwrap()
+            .unwrap();
+        let partitions: Vec<_> = table
+            .partitions()
+            .iter()
+            .map(|p| (p.number(), p.partition_type(), p.start_lba(), p.sectors()))
+            .collect();
+
+        assert_eq!(table.kind(), TableKind::Mbr);
+        assert_eq!(
+            partitions,
+            [
+                (1, PartitionType::Mbr(0x83), 2048, 2048),
+                (2, PartitionType::Mbr(0x0f), 4096, 8192),
+                (5, PartitionType::Mbr(0x83), 6144, 2048),
+                (6, PartitionType::Mbr(0x82), 10240, 2048),
+            ]
+        );
+    }
+
+    #[test]
+    fn mbr_rejected() {
+        let read = |disk: Vec<u8>| PartitionTable::read(&mut Cursor::new(disk)).unwrap();
+        let sectors = (DISK_SIZE / 512) as u32;
+
+        let mut valid = disk().into_inner();
+        boot_record(&mut valid, 0, &[(0x83, 2048, sectors - 2048)]);
+        assert!(read(valid.clone()).is_some());
+
+        // File system boot sectors
+        for (offset, signature) in [
+            (3, &b"NTFS    "[..]),
+            (3, b"EXFAT   "),
+            (0x36, b"FAT16   "),
+            (0x52, b"FAT32   "),
+        ] {
+            let mut disk = valid.clone();
+            disk[offset..offset + signature.len()].copy_from_slice(signature);
+            assert!(read(disk).is_none());
+        }
+
+        // Overlapping partitions
+        let mut overlapping = disk().into_inner();
+        boot_record(
+            &mut overlapping,
+            0,
+            &[(0x83, 2048, 4096), (0x83, 4096, 4096)],
+        );
+        assert!(read(overlapping).is_none());
+
+        // A partition running past the end of the disk
+        let mut truncated = disk().into_inner();
+        boot_record(
+            &mut truncated,
+            0,
+            &[(0x83, 2048, 2048), (0x83, 4096, sectors)],
+        );
+        assert!(read(truncated).is_none());
+    }
+}
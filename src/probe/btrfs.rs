@@ -0,0 +1,24 @@
+use super::{label, le32, read_at, uuid, Probe, Source, Usage};
+use std::io;
+
+const MAGIC: &[u8] = b"_BHRfS_M";
+
+/// The primary superblock is 64 KiB in, past anything a boot loader might use.
+const OFFSET: u64 = 0x10000;
+
+pub fn probe(source: &mut dyn Source) -> io::Result<Option<Probe>> {
+    let Some(sb) = read_at(source, OFFSET, 0x1000)? else {
+        return Ok(None);
+    };
+
+    if &sb[0x40..0x48] != MAGIC {
+        return Ok(None);
+    }
+
+    Ok(Some(Probe {
+        uuid: uuid(&sb[0x20..0x30]),
+        label: label(&sb[0x12b..0x22b]),
+        block_size: Some(le32(&sb, 0x90) as u64),
+        ..Probe::new("btrfs", Usage::FileSystem)
+    }))
+}
@@ -0,0 +1,45 @@
+use super::{label, le16, le32, read_at, uuid, Probe, Source, Usage};
+use std::io;
+
+const MAGIC: u16 = 0xef53;
+
+const HAS_JOURNAL: u32 = 0x4;
+const JOURNAL_DEV: u32 = 0x8;
+
+/// Features ext2 and ext3 don't have, so any of them means ext4.
+const EXT4_INCOMPAT: u32 = !0x16;
+const EXT4_RO_COMPAT: u32 = !0x7;
+
+pub fn probe(source: &mut dyn Source) -> io::Result<Option<Probe>> {
+    let Some(sb) = read_at(source, 1024, 1024)? else {
+        return Ok(None);
+    };
+
+    if le16(&sb, 0x38) != MAGIC {
+        return Ok(None);
+    }
+
+    let compat = le32(&sb, 0x5c);
+    let incompat = le32(&sb, 0x60);
+    let ro_compat = le32(&sb, 0x64);
+
+    let (fs_type, usage) = if incompat & JOURNAL_DEV != 0 {
+        ("jbd", Usage::Other)
+    } else if incompat & EXT4_INCOMPAT != 0 || ro_compat & EXT4_RO_COMPAT != 0 {
+        ("ext4", Usage::FileSystem)
+    } else if compat & HAS_JOURNAL != 0 {
+        ("ext3", Usage::FileSystem)
+    } else {
+        ("ext2", Usage::FileSystem)
+    };
+
+    let log_block_size = le32(&sb, 0x18);
+
+    Ok(Some(Probe {
+        uuid: uuid(&sb[0x68..0x78]),
+        label: label(&sb[0x78..0x88]),
+        version: Some(format!("{}.{}", le32(&sb, 0x4c), le16(&sb, 0x3e))),
+        block_size: (log_block_size < 16).then(|| 1024 << log_block_size),
+        ..Probe::new(fs_type, usage)
+    }))
+}
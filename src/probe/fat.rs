@@ -0,0 +1,145 @@
+use super::{label, le16, le32, read_at, serial, utf16_label, Probe, Source, Usage};
+use std::io;
+
+/// Clusters a FAT12 file system has fewer than, and FAT16 fewer than the next one.
+const FAT12_CLUSTERS: u64 = 4085;
+const FAT16_CLUSTERS: u64 = 65525;
+
+const EXFAT_MAGIC: &[u8] = b"EXFAT   ";
+
+/// Directory entry holding the label of an exFAT volume.
+const EXFAT_LABEL: u8 = 0x83;
+
+/// Entries in the root directory of an exFAT volume looked at for a label.
+const EXFAT_MAX_ENTRIES: u64 = 1024;
+
+/// FAT entries of exFAT that don't point to a next cluster, bad and end of chain.
+const EXFAT_BAD_CLUSTER: u32 = 0xffff_fff7;
+
+pub fn vfat(source: &mut dyn Source) -> io::Result<Option<Probe>> {
+    let Some(sb) = read_at(source, 0, 512)? else {
+        return Ok(None);
+    };
+
+    if sb[510..512] != [0x55, 0xaa] {
+        return Ok(None);
+    }
+
+    let (id, name) = if &sb[0x52..0x57] == b"FAT32" {
+        (0x43, 0x47)
+    } else if &sb[0x36..0x39] == b"FAT" {
+        (0x27, 0x2b)
+    } else {
+        return Ok(None);
+    };
+
+    let sector_size = le16(&sb, 11) as u64;
+    let cluster_sectors = sb[13] as u64;
+
+    if !(512..=4096).contains(&sector_size)
+        || !sector_size.is_power_of_two()
+        || !cluster_sectors.is_power_of_two()
+    {
+        return Ok(None);
+    }
+
+    let reserved = le16(&sb, 14) as u64;
+    let fats = sb[16] as u64;
+    let root_sectors = (le16(&sb, 17) as u64 * 32).div_ceil(sector_size);
+    let sectors = match le16(&sb, 19) {
+        0 => le32(&sb, 32) as u64,
+        sectors => sectors as u64,
+    };
+    let fat_sectors = match le16(&sb, 22) {
+        0 => le32(&sb, 36) as u64,
+        sectors => sectors as u64,
+    };
+
+    let clusters =
+        sectors.saturating_sub(reserved + fats * fat_sectors + root_sectors) / cluster_sectors;
+
+    // Only FAT32 keeps the size of its tables in the 32-bit field
+    let version = if le16(&sb, 22) == 0 {
+        "FAT32"
+    } else if clusters < FAT12_CLUSTERS {
+        "FAT12"
+    } else if clusters < FAT16_CLUSTERS {
+        "FAT16"
+    } else {
+        "FAT32"
+    };
+
+    Ok(Some(Probe {
+        uuid: Some(serial(le32(&sb, id))),
+        label: label(&sb[name..name + 11]).filter(|l| l != "NO NAME"),
+        version: Some(version.into()),
+        block_size: Some(sector_size),
+        ..Probe::new("vfat", Usage::FileSystem)
+    }))
+}
+
+pub fn exfat(source: &mut dyn Source) -> io::Result<Option<Probe>> {
+    let Some(sb) = read_at(source, 0, 512)? else {
+        return Ok(None);
+    };
+
+    if &sb[3..11] != EXFAT_MAGIC {
+        return Ok(None);
+    }
+
+    let sector_shift = sb[108] as u32;
+    let cluster_shift = sb[109] as u32;
+
+    if !(9..=12).contains(&sector_shift) || sector_shift + cluster_shift > 25 {
+        return Ok(None);
+    }
+
+    Ok(Some(Probe {
+        uuid: Some(serial(le32(&sb, 100))),
+        label: exfat_label(source, &sb)?,
+        version: Some(format!("{}.{}", sb[105], sb[104])),
+        block_size: Some(1 << sector_shift),
+        ..Probe::new("exfat", Usage::FileSystem)
+    }))
+}
+
+/// Looks for the label entry in the root directory of an exFAT volume, following its
+/// chain of clusters.
+fn exfat_label(source: &mut dyn Source, sb: &[u8]) -> io::Result<Option<String>> {
+    let sector_shift = sb[108] as u32;
+    let cluster_size = 1u64 << (sector_shift + sb[109] as u32);
+    let fat_offset = (le32(sb, 80) as u64) << sector_shift;
+    let heap_offset = (le32(sb, 88) as u64) << sector_shift;
+
+    let mut cluster = le32(sb, 96);
+    let mut entries = 0;
+
+    while (2..EXFAT_BAD_CLUSTER).contains(&cluster) && entries < EXFAT_MAX_ENTRIES {
+        let offset = heap_offset + (cluster as u64 - 2) * cluster_size;
+        let len = cluster_size.min((EXFAT_MAX_ENTRIES - entries) * 32);
+
+        let Some(directory) = read_at(source, offset, len as usize)? else {
+            return Ok(None);
+        };
+
+        for entry in directory.chunks_exact(32) {
+            match entry[0] {
+                // End of the directory
+                0 => return Ok(None),
+                EXFAT_LABEL => {
+                    let len = (entry[1] as usize).min(11);
+                    return Ok(utf16_label(&entry[2..2 + len * 2], false));
+                }
+                _ => entries += 1,
+            }
+        }
+
+        let Some(next) = read_at(source, fat_offset + cluster as u64 * 4, 4)? else {
+            return Ok(None);
+        };
+
+        cluster = le32(&next, 0);
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,84 @@
+use super::{label, le16, read_at, utf16_label, Probe, Source, Usage};
+use std::io;
+
+const MAGIC: &[u8] = b"CD001";
+
+/// Volume descriptors start at sector 16, after the system area.
+const DESCRIPTORS: u64 = 16 * SECTOR_SIZE;
+
+const SECTOR_SIZE: u64 = 2048;
+
+/// Volume descriptors looked at before giving up on a terminator.
+const MAX_DESCRIPTORS: u64 = 64;
+
+const PRIMARY: u8 = 1;
+const SUPPLEMENTARY: u8 = 2;
+const TERMINATOR: u8 = 255;
+
+/// Escape sequences of the UCS-2 levels a Joliet supplementary descriptor can use.
+const JOLIET: [&[u8]; 3] = [b"%/@", b"%/C", b"%/E"];
+
+pub fn probe(source: &mut dyn Source) -> io::Result<Option<Probe>> {
+    let mut primary = None;
+    let mut joliet = None;
+
+    for i in 0..MAX_DESCRIPTORS {
+        let Some(descriptor) = read_at(source, DESCRIPTORS + i * SECTOR_SIZE, 2048)? else {
+            break;
+        };
+
+        if &descriptor[1..6] != MAGIC {
+            break;
+        }
+
+        match descriptor[0] {
+            PRIMARY if primary.is_none() => primary = Some(descriptor),
+            SUPPLEMENTARY if JOLIET.contains(&&descriptor[88..91]) && joliet.is_none() => {
+                joliet = Some(descriptor)
+            }
+            TERMINATOR => break,
+            _ => {}
+        }
+    }
+
+    let Some(primary) = primary else {
+        return Ok(None);
+    };
+
+    // Joliet labels aren't limited to upper case letters
+    let label = match &joliet {
+        Some(joliet) => utf16_label(&joliet[40..72], true),
+        None => label(&primary[40..72]),
+    };
+
+    Ok(Some(Probe {
+        uuid: date(&primary[830..847]).or_else(|| date(&primary[813..830])),
+        label,
+        version: joliet.map(|_| "Joliet Extension".into()),
+        block_size: Some(le16(&primary, 128) as u64),
+        ..Probe::new("iso9660", Usage::FileSystem)
+    }))
+}
+
+/// Formats a modification or creation date, `YYYYMMDDHHMMSScc` in ASCII, the way udev
+/// shows it in `/dev/disk/by-uuid`. Returns `None` if the date isn't set.
+fn date(date: &[u8]) -> Option<String> {
+    let digits = &date[..16];
+
+    if !digits.iter().all(u8::is_ascii_digit) || digits.iter().all(|&d| d == b'0') {
+        return None;
+    }
+
+    let field = |range: std::ops::Range<usize>| String::from_utf8_lossy(&digits[range]);
+
+    Some(format!(
+        "{}-{}-{}-{}-{}-{}-{}",
+        field(0..4),
+        field(4..6),
+        field(6..8),
+        field(8..10),
+        field(10..12),
+        field(12..14),
+        field(14..16)
+    ))
+}
@@ -0,0 +1,27 @@
+use super::{be16, label, read_at, Probe, Source, Usage};
+use std::io;
+
+const MAGIC: &[u8] = b"LUKS\xba\xbe";
+
+pub fn probe(source: &mut dyn Source) -> io::Result<Option<Probe>> {
+    let Some(header) = read_at(source, 0, 512)? else {
+        return Ok(None);
+    };
+
+    if !header.starts_with(MAGIC) {
+        return Ok(None);
+    }
+
+    let version = be16(&header, 6);
+
+    // The UUID is stored as text, and only LUKS2 has a label
+    Ok(Some(Probe {
+        uuid: label(&header[168..208]),
+        label: match version {
+            2 => label(&header[24..72]),
+            _ => None,
+        },
+        version: Some(version.to_string()),
+        ..Probe::new("crypto_LUKS", Usage::Crypto)
+    }))
+}
@@ -0,0 +1,51 @@
+use super::{le32, le64, read_at, Probe, Source, Usage};
+use std::io;
+
+const LABEL: &[u8] = b"LABELONE";
+const LABEL_TYPE: &[u8] = b"LVM2 001";
+
+/// The label can be in any of the first four sectors.
+const LABEL_SECTORS: u64 = 4;
+
+const SECTOR_SIZE: u64 = 512;
+
+/// Lengths of the groups the 32 characters of a physical volume UUID are shown in.
+const UUID_GROUPS: [usize; 7] = [6, 4, 4, 4, 4, 4, 6];
+
+pub fn probe(source: &mut dyn Source) -> io::Result<Option<Probe>> {
+    for sector in 0..LABEL_SECTORS {
+        let Some(label) = read_at(source, sector * SECTOR_SIZE, SECTOR_SIZE as usize)? else {
+            return Ok(None);
+        };
+
+        if !label.starts_with(LABEL) || le64(&label, 8) != sector || &label[24..32] != LABEL_TYPE {
+            continue;
+        }
+
+        // The physical volume header follows, starting with its UUID
+        let offset = le32(&label, 20) as usize;
+        let Some(id) = label.get(offset..offset + 32) else {
+            return Ok(None);
+        };
+
+        let mut uuid = String::new();
+        let mut start = 0;
+
+        for len in UUID_GROUPS {
+            if start > 0 {
+                uuid.push('-');
+            }
+
+            uuid.push_str(&String::from_utf8_lossy(&id[start..start + len]));
+            start += len;
+        }
+
+        return Ok(Some(Probe {
+            uuid: Some(uuid),
+            version: Some(String::from_utf8_lossy(LABEL_TYPE).into()),
+            ..Probe::new("LVM2_member", Usage::Raid)
+        }));
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,639 @@
+//! Superblock probing, public as `device::probe`.
+
+mod btrfs;
+mod ext;
+mod fat;
+mod iso9660;
+mod luks;
+mod lvm;
+mod ntfs;
+mod squashfs;
+mod swap;
+mod udf;
+mod xfs;
+
+use crate::Result;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// What the content found by a [`Probe`] is used for, as libblkid reports in `USAGE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Usage {
+    /// A file system that can be mounted.
+    FileSystem,
+    /// An encrypted volume, such as LUKS.
+    Crypto,
+    /// A member of a volume manager or RAID array, such as an LVM physical volume.
+    Raid,
+    /// Anything else, such as swap.
+    Other,
+}
+
+/// What was found in the superblock of a device or image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Probe {
+    fs_type: &'static str,
+    usage: Usage,
+    uuid: Option<String>,
+    label: Option<String>,
+    version: Option<String>,
+    block_size: Option<u64>,
+}
+
+impl Probe {
+    fn new(fs_type: &'static str, usage: Usage) -> Self {
+        Probe {
+            fs_type,
+            usage,
+            uuid: None,
+            label: None,
+            version: None,
+            block_size: None,
+        }
+    }
+
+    /// Type of the content, named as libblkid and `mount` do, such as `ext4`, `vfat`,
+    /// `swap`, `crypto_LUKS` or `LVM2_member`.
+    pub fn fs_type(&self) -> &str {
+        self.fs_type
+    }
+
+    /// What the content is used for.
+    pub fn usage(&self) -> Usage {
+        self.usage
+    }
+
+    /// UUID of the content, in the form used by `UUID=` volumes and `/dev/disk/by-uuid`.
+    ///
+    /// It is not always a real UUID: FAT and exFAT have a `ABCD-1234` serial number, NTFS a
+    /// 64-bit one and iso9660 a creation date.
+    pub fn uuid(&self) -> Option<&str> {
+        self.uuid.as_deref()
+    }
+
+    /// Label of the content, if it has one.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Version of the format, such as `1.0` for ext4, `FAT32` for vfat or `2` for LUKS2.
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// Block size of the content in bytes, if the format records one. FAT, exFAT and NTFS
+    /// report their sector size rather than their cluster size, as libblkid does.
+    pub fn block_size(&self) -> Option<u64> {
+        self.block_size
+    }
+}
+
+type Prober = fn(&mut dyn Source) -> io::Result<Option<Probe>>;
+
+// Formats that may keep older superblocks around come first: LUKS and LVM leave the start
+// of the device alone, and UDF discs usually carry an iso9660 bridge.
+const PROBERS: &[Prober] = &[
+    luks::probe,
+    lvm::probe,
+    ext::probe,
+    xfs::probe,
+    btrfs::probe,
+    squashfs::probe,
+    fat::exfat,
+    ntfs::probe,
+    fat::vfat,
+    udf::probe,
+    iso9660::probe,
+    swap::probe,
+];
+
+trait Source: Read + Seek {}
+
+impl<T: Read + Seek> Source for T {}
+
+/// Looks for a known superblock in `source`, a block device or an image file.
+///
+/// ext2/3/4, xfs, btrfs, FAT12/16/32, exFAT, NTFS, iso9660, UDF, squashfs, swap, LUKS and
+/// LVM2 physical volumes are detected. Only the superblocks are read, so this works
+/// offline, without udev or any kernel support for the format.
+///
+/// Returns `None` if nothing known is found.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(feature = "device")]
+/// use disket::device::probe;
+/// use std::error::Error;
+/// use std::fs::File;
+///
+/// # #[cfg(feature = "device")]
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     if let Some(probe) = probe::probe(&mut File::open("rootfs.img")?)? {
+///         println!(
+///             "{} {} {}",
+///             probe.fs_type(),
+///             probe.uuid().unwrap_or_default(),
+///             probe.label().unwrap_or_default(),
+///         );
+///     }
+///
+///     Ok(())
+/// }
+/// # #[cfg(not(feature = "device"))]
+/// # fn main() {}
+/// ```
+pub fn probe<R: Read + Seek>(source: &mut R) -> Result<Option<Probe>> {
+    for prober in PROBERS {
+        if let Some(probe) = prober(source)? {
+            return Ok(Some(probe));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Reads `len` bytes at `offset`. Returns `None` if the source is too small.
+fn read_at(source: &mut dyn Source, offset: u64, len: usize) -> io::Result<Option<Vec<u8>>> {
+    let mut buf = vec![0; len];
+    source.seek(SeekFrom::Start(offset))?;
+
+    match source.read_exact(&mut buf) {
+        Ok(()) => Ok(Some(buf)),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn le16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn le32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+fn le64(buf: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap())
+}
+
+fn be16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn be32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+fn uuid(bytes: &[u8]) -> Option<String> {
+    if bytes.iter().all(|&b| b == 0) {
+        return None;
+    }
+
+    let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+
+    Some(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    ))
+}
+
+/// Formats a 32-bit volume serial number the way FAT and exFAT tools do.
+fn serial(serial: u32) -> String {
+    format!("{:04X}-{:04X}", serial >> 16, serial & 0xffff)
+}
+
+fn label(bytes: &[u8]) -> Option<String> {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    let label = String::from_utf8_lossy(&bytes[..end])
+        .trim_end()
+        .to_string();
+
+    (!label.is_empty()).then_some(label)
+}
+
+/// Decodes a label stored as UTF-16, little-endian unless `big_endian` is set.
+fn utf16_label(bytes: &[u8], big_endian: bool) -> Option<String> {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|c| match big_endian {
+            true => u16::from_be_bytes([c[0], c[1]]),
+            false => u16::from_le_bytes([c[0], c[1]]),
+        })
+        .take_while(|&c| c != 0)
+        .collect();
+
+    let label = String::from_utf16_lossy(&units).trim_end().to_string();
+
+    (!label.is_empty()).then_some(label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const UUID: [u8; 16] = [
+        0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0, 0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde,
+        0xf0,
+    ];
+    const UUID_STR: &str = "12345678-9abc-def0-1234-56789abcdef0";
+
+    fn image(len: usize, fields: &[(usize, &[u8])]) -> Vec<u8> {
+        let mut image = vec![0; len];
+
+        for (offset, bytes) in fields {
+            image[*offset..offset + bytes.len()].copy_from_slice(bytes);
+        }
+
+        image
+    }
+
+    fn detect(image: Vec<u8>) -> Option<Probe> {
+        probe(&mut Cursor::new(image)).unwrap()
+    }
+
+    fn check(image: Vec<u8>, fs_type: &str, uuid: &str, label: Option<&str>, version: &str) {
+        let probe = detect(image).unwrap();
+
+        assert_eq!(probe.fs_type(), fs_type);
+        assert_eq!(probe.uuid(), Some(uuid));
+        assert_eq!(probe.label(), label);
+        assert_eq!(probe.version(), Some(version));
+    }
+
+    /// Encodes `s` as a `dstring` of UDF filling `len` bytes.
+    fn dstring(s: &str, len: usize) -> Vec<u8> {
+        let mut field = vec![0; len];
+        field[0] = 8;
+        field[1..=s.len()].copy_from_slice(s.as_bytes());
+        field[len - 1] = s.len() as u8 + 1;
+        field
+    }
+
+    /// Builds an NTFS boot sector with 4 KiB clusters, and the volume record of its MFT
+    /// at cluster 4 with 1 KiB records, holding the label and version.
+    fn ntfs_image() -> Vec<u8> {
+        let mut record = vec![0; 1024];
+        let name: Vec<u8> = "Windows"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let mut information = [0; 12];
+        information[8..10].copy_from_slice(&[3, 1]);
+
+        record[..4].copy_from_slice(b"FILE");
+        record[4..8].copy_from_slice(&[0x30, 0, 3, 0]);
+        record[0x14..0x16].copy_from_slice(&0x38u16.to_le_bytes());
+
+        let mut offset = 0x38;
+
+        for (kind, value) in [(0x60u32, &name[..]), (0x70, &information[..])] {
+            let len = (0x18 + value.len()).next_multiple_of(8);
+            let attribute = &mut record[offset..offset + len];
+
+            attribute[..4].copy_from_slice(&kind.to_le_bytes());
+            attribute[4..8].copy_from_slice(&(len as u32).to_le_bytes());
+            attribute[0x10..0x14].copy_from_slice(&(value.len() as u32).to_le_bytes());
+            attribute[0x14..0x16].copy_from_slice(&0x18u16.to_le_bytes());
+            attribute[0x18..0x18 + value.len()].copy_from_slice(value);
+            offset += len;
+        }
+
+        record[offset..offset + 4].fill(0xff);
+
+        // Move the last two bytes of each 512 bytes into the update sequence
+        record[0x30..0x32].copy_from_slice(&[1, 0]);
+
+        for i in 1..3 {
+            record.copy_within(i * 512 - 2..i * 512, 0x30 + i * 2);
+            record[i * 512 - 2..i * 512].copy_from_slice(&[1, 0]);
+        }
+
+        image(
+            64 << 10,
+            &[
+                (3, b"NTFS    "),
+                (11, &512u16.to_le_bytes()),
+                (13, &[8]),
+                (0x30, &4u64.to_le_bytes()),
+                (0x40, &[0xf6]),
+                (0x48, &0x0123_4567_89ab_cdefu64.to_le_bytes()),
+                (4 * 4096 + 3 * 1024, &record),
+            ],
+        )
+    }
+
+    #[test]
+    fn ext() {
+        let ext4 = |incompat: u32, compat: u32| {
+            image(
+                4096,
+                &[
+                    (1024 + 0x18, &2u32.to_le_bytes()),
+                    (1024 + 0x38, &0xef53u16.to_le_bytes()),
+                    (1024 + 0x4c, &1u32.to_le_bytes()),
+                    (1024 + 0x5c, &compat.to_le_bytes()),
+                    (1024 + 0x60, &incompat.to_le_bytes()),
+                    (1024 + 0x68, &UUID),
+                    (1024 + 0x78, b"root"),
+                ],
+            )
+        };
+
+        check(ext4(0x40, 0x4), "ext4", UUID_STR, Some("root"), "1.0");
+        assert_eq!(detect(ext4(0x40, 0x4)).unwrap().block_size(), Some(4096));
+        assert_eq!(detect(ext4(0x2, 0x4)).unwrap().fs_type(), "ext3");
+        assert_eq!(detect(ext4(0x2, 0)).unwrap().fs_type(), "ext2");
+    }
+
+    #[test]
+    fn xfs() {
+        let xfs = image(
+            512,
+            &[
+                (0, b"XFSB"),
+                (4, &4096u32.to_be_bytes()),
+                (32, &UUID),
+                (100, &0xb4a5u16.to_be_bytes()),
+                (108, b"data"),
+            ],
+        );
+
+        check(xfs, "xfs", UUID_STR, Some("data"), "5");
+    }
+
+    #[test]
+    fn btrfs() {
+        let btrfs = image(
+            0x11000,
+            &[
+                (0x10020, &UUID),
+                (0x10040, b"_BHRfS_M"),
+                (0x10090, &4096u32.to_le_bytes()),
+                (0x1012b, b"pool"),
+            ],
+        );
+        let probe = detect(btrfs).unwrap();
+
+        assert_eq!(probe.fs_type(), "btrfs");
+        assert_eq!(probe.uuid(), Some(UUID_STR));
+        assert_eq!(probe.label(), Some("pool"));
+        assert_eq!(probe.block_size(), Some(4096));
+    }
+
+    #[test]
+    fn squashfs() {
+        let squashfs = image(
+            96,
+            &[
+                (0, b"hsqs"),
+                (12, &131072u32.to_le_bytes()),
+                (28, &[4, 0, 0, 0]),
+            ],
+        );
+        let probe = detect(squashfs).unwrap();
+
+        assert_eq!(probe.fs_type(), "squashfs");
+        assert_eq!((probe.uuid(), probe.label()), (None, None));
+        assert_eq!(probe.version(), Some("4.0"));
+        assert_eq!(probe.block_size(), Some(131072));
+    }
+
+    #[test]
+    fn vfat() {
+        let fat32 = image(
+            512,
+            &[
+                (11, &512u16.to_le_bytes()),
+                (13, &[8]),
+                (0x43, &0x1234_abcdu32.to_le_bytes()),
+                (0x47, b"BOOT       "),
+                (0x52, b"FAT32   "),
+                (510, &[0x55, 0xaa]),
+            ],
+        );
+
+        check(fat32, "vfat", "1234-ABCD", Some("BOOT"), "FAT32");
+
+        // 20000 sectors, less the reserved one, two tables of 20 and 32 for the root
+        // directory, make 4981 clusters of 4 sectors
+        let fat16 = image(
+            512,
+            &[
+                (11, &512u16.to_le_bytes()),
+                (13, &[4]),
+                (14, &1u16.to_le_bytes()),
+                (16, &[2]),
+                (17, &512u16.to_le_bytes()),
+                (19, &20000u16.to_le_bytes()),
+                (22, &20u16.to_le_bytes()),
+                (0x27, &0x0000_beefu32.to_le_bytes()),
+                (0x2b, b"NO NAME    "),
+                (0x36, b"FAT16   "),
+                (510, &[0x55, 0xaa]),
+            ],
+        );
+
+        check(fat16, "vfat", "0000-BEEF", None, "FAT16");
+    }
+
+    #[test]
+    fn exfat() {
+        let label: Vec<u8> = "USB".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let exfat = image(
+            64 << 10,
+            &[
+                (3, b"EXFAT   "),
+                (80, &32u32.to_le_bytes()),
+                (88, &64u32.to_le_bytes()),
+                (96, &2u32.to_le_bytes()),
+                (100, &0x1234_abcdu32.to_le_bytes()),
+                (104, &[0, 1]),
+                (108, &[9, 3]),
+                // The root directory, at the first cluster of the heap
+                (32768, &[0x81; 32]),
+                (32768 + 32, &[0x83, 3]),
+                (32768 + 34, &label),
+            ],
+        );
+
+        check(exfat, "exfat", "1234-ABCD", Some("USB"), "1.0");
+    }
+
+    #[test]
+    fn ntfs() {
+        check(
+            ntfs_image(),
+            "ntfs",
+            "0123456789ABCDEF",
+            Some("Windows"),
+            "3.1",
+        );
+    }
+
+    #[test]
+    fn ntfs_damaged_boot_sector() {
+        let damaged = |fields: &[(usize, &[u8])]| {
+            let mut image = ntfs_image();
+
+            for (offset, bytes) in fields {
+                image[*offset..offset + bytes.len()].copy_from_slice(bytes);
+            }
+
+            image
+        };
+
+        for image in [
+            // 8 byte records, shorter than their header, with the volume one looking valid
+            damaged(&[(0x40, &[0xfd]), (4 * 4096 + 3 * 8, b"FILE\x06\x00\x01\x00")]),
+            // Records of 127 clusters, and clusters of 512 MiB making records of 2 GiB
+            damaged(&[(0x40, &[0x7f])]),
+            damaged(&[(13, &[0xec]), (0x40, &[4])]),
+            // An MFT past the end of any disk
+            damaged(&[(0x30, &u64::MAX.to_le_bytes())]),
+        ] {
+            assert_eq!(detect(image), None);
+        }
+
+        // A damaged record only loses the label and version
+        let probe = detect(damaged(&[(4 * 4096 + 3 * 1024 + 510, &[9])])).unwrap();
+        assert_eq!(probe.fs_type(), "ntfs");
+        assert_eq!((probe.label(), probe.version()), (None, None));
+    }
+
+    #[test]
+    fn swap() {
+        let swap = image(
+            8192,
+            &[
+                (1024, &1u32.to_le_bytes()),
+                (1024 + 12, &UUID),
+                (1024 + 28, b"swap"),
+                (4096 - 10, b"SWAPSPACE2"),
+            ],
+        );
+
+        check(swap, "swap", UUID_STR, Some("swap"), "1");
+    }
+
+    #[test]
+    fn luks() {
+        let luks = |version: u16| {
+            image(
+                512,
+                &[
+                    (0, b"LUKS\xba\xbe"),
+                    (6, &version.to_be_bytes()),
+                    (24, b"secret"),
+                    (168, UUID_STR.as_bytes()),
+                ],
+            )
+        };
+
+        check(luks(2), "crypto_LUKS", UUID_STR, Some("secret"), "2");
+        check(luks(1), "crypto_LUKS", UUID_STR, None, "1");
+    }
+
+    #[test]
+    fn lvm() {
+        let lvm = image(
+            2048,
+            &[
+                (512, b"LABELONE"),
+                (512 + 8, &1u64.to_le_bytes()),
+                (512 + 20, &32u32.to_le_bytes()),
+                (512 + 24, b"LVM2 001"),
+                (512 + 32, b"abcdefGHIJklmnOPQRstuvWXYZ012345"),
+            ],
+        );
+
+        check(
+            lvm,
+            "LVM2_member",
+            "abcdef-GHIJ-klmn-OPQR-stuv-WXYZ-012345",
+            None,
+            "LVM2 001",
+        );
+    }
+
+    #[test]
+    fn iso9660() {
+        let primary = |descriptor: usize| {
+            vec![
+                (16 * 2048, &b"\x01CD001"[..]),
+                (16 * 2048 + 40, b"CDROM                           "),
+                (16 * 2048 + 128, &[0x00, 0x08]),
+                (16 * 2048 + 813, b"2024010212345600"),
+                (descriptor * 2048, b"\xffCD001"),
+            ]
+        };
+
+        let probe = detect(image(20 * 2048, &primary(17))).unwrap();
+
+        assert_eq!(probe.fs_type(), "iso9660");
+        assert_eq!(probe.uuid(), Some("2024-01-02-12-34-56-00"));
+        assert_eq!(probe.label(), Some("CDROM"));
+        assert_eq!(probe.version(), None);
+
+        let label: Vec<u8> = "Joliet disc"
+            .encode_utf16()
+            .flat_map(u16::to_be_bytes)
+            .collect();
+        let mut joliet = primary(18);
+        joliet.extend([
+            (17 * 2048, &b"\x02CD001"[..]),
+            (17 * 2048 + 40, &label),
+            (17 * 2048 + 88, b"%/E"),
+        ]);
+
+        check(
+            image(20 * 2048, &joliet),
+            "iso9660",
+            "2024-01-02-12-34-56-00",
+            Some("Joliet disc"),
+            "Joliet Extension",
+        );
+    }
+
+    #[test]
+    fn udf() {
+        let descriptor = |lba: usize, tag: u16| {
+            let mut descriptor = vec![0; 512];
+            descriptor[..2].copy_from_slice(&tag.to_le_bytes());
+            descriptor[12..16].copy_from_slice(&(lba as u32).to_le_bytes());
+            descriptor
+        };
+
+        let mut anchor = descriptor(256, 2);
+        anchor[16..20].copy_from_slice(&(4u32 * 2048).to_le_bytes());
+        anchor[20..24].copy_from_slice(&257u32.to_le_bytes());
+
+        let mut primary = descriptor(257, 1);
+        primary[24..56].copy_from_slice(&dstring("LinuxUDF", 32));
+        primary[72..200].copy_from_slice(&dstring("0123456789ABCDEFvolume", 128));
+
+        let mut logical = descriptor(258, 6);
+        logical[84..212].copy_from_slice(&dstring("Backup", 128));
+        logical[212..216].copy_from_slice(&2048u32.to_le_bytes());
+        logical[240..242].copy_from_slice(&0x0201u16.to_le_bytes());
+
+        let udf = image(
+            260 * 2048,
+            &[
+                (32768, b"\x00BEA01"),
+                (32768 + 2048, b"\x00NSR02"),
+                (32768 + 4096, b"\x00TEA01"),
+                (256 * 2048, &anchor),
+                (257 * 2048, &primary),
+                (258 * 2048, &logical),
+                (259 * 2048, &descriptor(259, 8)),
+            ],
+        );
+
+        check(udf, "udf", "0123456789abcdef", Some("Backup"), "2.01");
+    }
+}
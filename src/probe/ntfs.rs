@@ -0,0 +1,144 @@
+use super::{le16, le32, le64, read_at, utf16_label, Probe, Source, Usage};
+use std::io;
+
+const MAGIC: &[u8] = b"NTFS    ";
+
+const RECORD_MAGIC: &[u8] = b"FILE";
+
+/// Number of the MFT record describing the volume itself.
+const VOLUME_RECORD: u64 = 3;
+
+const VOLUME_NAME: u32 = 0x60;
+const VOLUME_INFORMATION: u32 = 0x70;
+const END_OF_ATTRIBUTES: u32 = 0xffff_ffff;
+
+/// Update sequences protect every 512 bytes of a record, whatever the sector size.
+const FIXUP_STRIDE: usize = 512;
+
+pub fn probe(source: &mut dyn Source) -> io::Result<Option<Probe>> {
+    let Some(sb) = read_at(source, 0, 512)? else {
+        return Ok(None);
+    };
+
+    if &sb[3..11] != MAGIC {
+        return Ok(None);
+    }
+
+    let sector_size = le16(&sb, 11) as u64;
+
+    if !(256..=4096).contains(&sector_size) || !sector_size.is_power_of_two() {
+        return Ok(None);
+    }
+
+    // Large clusters are stored as a negative power of two
+    let cluster_size = match sb[13] {
+        n if n > 0x80 && 0x100 - n as u32 <= 20 => sector_size << (0x100 - n as u32),
+        n if n.is_power_of_two() => sector_size * n as u64,
+        _ => return Ok(None),
+    };
+
+    let record_size = match sb[0x40] as i8 {
+        n if n < 0 && n > -32 => 1 << -n,
+        n if n > 0 => cluster_size * n as u64,
+        _ => return Ok(None),
+    };
+
+    // Records are at least as large as their header, and no tool makes them over 64 KiB
+    if !(256..=65536).contains(&record_size) {
+        return Ok(None);
+    }
+
+    let mut probe = Probe {
+        uuid: Some(format!("{:016X}", le64(&sb, 0x48))),
+        block_size: Some(sector_size),
+        ..Probe::new("ntfs", Usage::FileSystem)
+    };
+
+    let Some(offset) = le64(&sb, 0x30)
+        .checked_mul(cluster_size)
+        .and_then(|mft| mft.checked_add(VOLUME_RECORD * record_size))
+    else {
+        return Ok(None);
+    };
+
+    if let Some(record) = record(source, offset, record_size as usize)? {
+        for (kind, value) in attributes(&record) {
+            match kind {
+                VOLUME_NAME => probe.label = utf16_label(value, false),
+                VOLUME_INFORMATION if value.len() >= 10 => {
+                    probe.version = Some(format!("{}.{}", value[8], value[9]))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(Some(probe))
+}
+
+/// Reads the MFT record at `offset` and undoes its update sequence. Returns `None` if it
+/// is damaged.
+fn record(source: &mut dyn Source, offset: u64, size: usize) -> io::Result<Option<Vec<u8>>> {
+    let Some(mut record) = read_at(source, offset, size)? else {
+        return Ok(None);
+    };
+
+    if !record.starts_with(RECORD_MAGIC) {
+        return Ok(None);
+    }
+
+    // The last two bytes of each stride hold a check value, and the real bytes are kept
+    // in the update sequence after it
+    let sequence = le16(&record, 4) as usize;
+    let count = le16(&record, 6) as usize;
+
+    if count == 0 || sequence + count * 2 > size || (count - 1) * FIXUP_STRIDE > size {
+        return Ok(None);
+    }
+
+    let check = [record[sequence], record[sequence + 1]];
+
+    for i in 1..count {
+        let end = i * FIXUP_STRIDE;
+
+        if record[end - 2..end] != check {
+            return Ok(None);
+        }
+
+        let fixup = sequence + i * 2;
+        record.copy_within(fixup..fixup + 2, end - 2);
+    }
+
+    Ok(Some(record))
+}
+
+/// Returns the type and value of the resident attributes of an MFT record.
+fn attributes(record: &[u8]) -> Vec<(u32, &[u8])> {
+    let mut attributes = Vec::new();
+    let mut offset = le16(record, 0x14) as usize;
+
+    while offset + 24 <= record.len() {
+        let kind = le32(record, offset);
+        let len = le32(record, offset + 4) as usize;
+
+        if kind == END_OF_ATTRIBUTES || len < 24 || offset + len > record.len() {
+            break;
+        }
+
+        let attribute = &record[offset..offset + len];
+
+        // Non-resident attributes keep their value elsewhere, and these never are
+        if attribute[8] == 0 {
+            let value_len = le32(attribute, 0x10) as usize;
+            let value_offset = le16(attribute, 0x14) as usize;
+
+            if let Some(value) = attribute.get(value_offset..value_offset + value_len) {
+                attributes.push((kind, value));
+            }
+        }
+
+        offset += len;
+    }
+
+    attributes
+}
@@ -0,0 +1,21 @@
+use super::{le16, le32, read_at, Probe, Source, Usage};
+use std::io;
+
+const MAGIC: &[u8] = b"hsqs";
+
+pub fn probe(source: &mut dyn Source) -> io::Result<Option<Probe>> {
+    let Some(sb) = read_at(source, 0, 96)? else {
+        return Ok(None);
+    };
+
+    if !sb.starts_with(MAGIC) {
+        return Ok(None);
+    }
+
+    // squashfs has neither a UUID nor a label
+    Ok(Some(Probe {
+        version: Some(format!("{}.{}", le16(&sb, 28), le16(&sb, 30))),
+        block_size: Some(le32(&sb, 12) as u64),
+        ..Probe::new("squashfs", Usage::FileSystem)
+    }))
+}
@@ -0,0 +1,50 @@
+use super::{label, le32, read_at, uuid, Probe, Source, Usage};
+use std::io;
+
+/// The signature is at the end of the first page, whose size depends on the
+/// architecture that created the swap area.
+const PAGE_SIZES: [u64; 4] = [4096, 8192, 16384, 65536];
+
+const MAGIC: &[u8] = b"SWAPSPACE2";
+const MAGIC_V0: &[u8] = b"SWAP-SPACE";
+
+pub fn probe(source: &mut dyn Source) -> io::Result<Option<Probe>> {
+    for page_size in PAGE_SIZES {
+        let Some(magic) = read_at(source, page_size - 10, 10)? else {
+            return Ok(None);
+        };
+
+        if magic == MAGIC_V0 {
+            return Ok(Some(Probe {
+                version: Some("0".into()),
+                block_size: Some(page_size),
+                ..Probe::new("swap", Usage::Other)
+            }));
+        }
+
+        if magic != MAGIC {
+            continue;
+        }
+
+        // The header follows the space left for a boot sector and a disk label
+        let Some(header) = read_at(source, 1024, 44)? else {
+            return Ok(None);
+        };
+
+        let version = le32(&header, 0);
+
+        if version != 1 {
+            return Ok(None);
+        }
+
+        return Ok(Some(Probe {
+            uuid: uuid(&header[12..28]),
+            label: label(&header[28..44]),
+            version: Some(version.to_string()),
+            block_size: Some(page_size),
+            ..Probe::new("swap", Usage::Other)
+        }));
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,150 @@
+use super::{le16, le32, read_at, Probe, Source, Usage};
+use std::io;
+
+/// The volume recognition sequence starts 32 KiB in, whatever the block size.
+const RECOGNITION: u64 = 32768;
+
+/// Recognition descriptors looked at before giving up.
+const MAX_RECOGNITION: u64 = 64;
+
+/// Identifiers of the descriptors announcing an UDF volume, for UDF 1.x and 2.x or later.
+const NSR: [&[u8]; 2] = [b"NSR02", b"NSR03"];
+
+/// Block sizes the anchor volume descriptor pointer is looked for with, most common first.
+const BLOCK_SIZES: [u64; 4] = [2048, 512, 1024, 4096];
+
+/// The anchor is at block 256.
+const ANCHOR: u64 = 256;
+
+/// Descriptors of the volume descriptor sequence looked at before giving up.
+const MAX_DESCRIPTORS: u64 = 64;
+
+const TAG_PRIMARY: u16 = 1;
+const TAG_ANCHOR: u16 = 2;
+const TAG_LOGICAL: u16 = 6;
+const TAG_TERMINATOR: u16 = 8;
+
+pub fn probe(source: &mut dyn Source) -> io::Result<Option<Probe>> {
+    if !recognized(source)? {
+        return Ok(None);
+    }
+
+    for block_size in BLOCK_SIZES {
+        let Some(anchor) = read_at(source, ANCHOR * block_size, 512)? else {
+            continue;
+        };
+
+        if le16(&anchor, 0) != TAG_ANCHOR || le32(&anchor, 12) as u64 != ANCHOR {
+            continue;
+        }
+
+        // The extent of the main volume descriptor sequence
+        let len = le32(&anchor, 16) as u64 / block_size;
+        let location = le32(&anchor, 20) as u64;
+
+        let mut probe = Probe::new("udf", Usage::FileSystem);
+
+        for i in 0..len.min(MAX_DESCRIPTORS) {
+            let lba = location + i;
+            let Some(descriptor) = read_at(source, lba * block_size, 512)? else {
+                break;
+            };
+
+            if le32(&descriptor, 12) as u64 != lba {
+                break;
+            }
+
+            match le16(&descriptor, 0) {
+                TAG_PRIMARY => {
+                    probe.uuid = volume_set_uuid(&descriptor[72..200]);
+                    probe.label = probe.label.or_else(|| dstring(&descriptor[24..56]));
+                }
+                TAG_LOGICAL => {
+                    let revision = le16(&descriptor, 240);
+
+                    probe.label = dstring(&descriptor[84..212]).or(probe.label);
+                    probe.block_size = Some(le32(&descriptor, 212) as u64);
+                    probe.version = Some(format!("{:x}.{:02x}", revision >> 8, revision & 0xff));
+                }
+                TAG_TERMINATOR => break,
+                _ => {}
+            }
+        }
+
+        return Ok(Some(probe));
+    }
+
+    Ok(None)
+}
+
+/// Returns `true` if the volume recognition sequence has an NSR descriptor.
+fn recognized(source: &mut dyn Source) -> io::Result<bool> {
+    for i in 0..MAX_RECOGNITION {
+        let Some(descriptor) = read_at(source, RECOGNITION + i * 2048, 6)? else {
+            return Ok(false);
+        };
+
+        match &descriptor[1..6] {
+            id if NSR.contains(&id) => return Ok(true),
+            b"BEA01" | b"BOOT2" | b"CD001" | b"CDW02" | b"TEA01" => {}
+            _ => return Ok(false),
+        }
+    }
+
+    Ok(false)
+}
+
+/// Decodes a `dstring`, whose first byte says whether characters take 8 or 16 bits and
+/// whose last byte is the length used.
+fn dstring(field: &[u8]) -> Option<String> {
+    let len = (*field.last()? as usize).min(field.len() - 1);
+
+    if len < 2 {
+        return None;
+    }
+
+    let chars = &field[1..len];
+    let string: String = match field[0] {
+        8 => chars.iter().map(|&c| c as char).collect(),
+        16 => char::decode_utf16(
+            chars
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]])),
+        )
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect(),
+        _ => return None,
+    };
+
+    let string = string.trim_end().to_string();
+
+    (!string.is_empty()).then_some(string)
+}
+
+/// Derives a UUID from the volume set identifier the way libblkid does. It should start
+/// with 16 hex digits, but many tools put fewer or none, so the bytes that aren't digits
+/// are turned into hex instead.
+fn volume_set_uuid(field: &[u8]) -> Option<String> {
+    let id = dstring(field)?;
+    let bytes = id.as_bytes();
+
+    if bytes.len() < 8 {
+        return None;
+    }
+
+    let hex = |bytes: &[u8]| -> String { bytes.iter().map(|b| format!("{b:02x}")).collect() };
+    let digits = bytes
+        .iter()
+        .take(16)
+        .take_while(|b| b.is_ascii_hexdigit())
+        .count();
+
+    Some(match digits {
+        0..=7 => hex(&bytes[..8]),
+        8..=15 if bytes.len() >= 12 => {
+            format!("{}{}", id[..8].to_ascii_lowercase(), hex(&bytes[8..12]))
+        }
+        8..=15 => return None,
+        _ => id[..16].to_ascii_lowercase(),
+    })
+}
@@ -0,0 +1,25 @@
+use super::{be16, be32, label, read_at, uuid, Probe, Source, Usage};
+use std::io;
+
+const MAGIC: &[u8] = b"XFSB";
+
+/// The low bits of the version field hold the version number.
+const VERSION_MASK: u16 = 0xf;
+
+pub fn probe(source: &mut dyn Source) -> io::Result<Option<Probe>> {
+    let Some(sb) = read_at(source, 0, 512)? else {
+        return Ok(None);
+    };
+
+    if !sb.starts_with(MAGIC) {
+        return Ok(None);
+    }
+
+    Ok(Some(Probe {
+        uuid: uuid(&sb[32..48]),
+        label: label(&sb[108..120]),
+        version: Some((be16(&sb, 100) & VERSION_MASK).to_string()),
+        block_size: Some(be32(&sb, 4) as u64),
+        ..Probe::new("xfs", Usage::FileSystem)
+    }))
+}